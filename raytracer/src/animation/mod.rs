@@ -0,0 +1,217 @@
+pub mod track;
+
+use crate::{
+    basic::{camera::Camera, quaternion::Quaternion, transform::Transform, vec::Vec3},
+    optimization::aabb::AABB,
+};
+use track::Track;
+
+// 帧号与时间的对应关系，时间以秒为单位
+#[derive(Clone, Copy)]
+pub struct Timeline {
+    pub fps: f64,
+    pub shutter: f64, // 快门打开的时间占一帧的比例，0.5 即 180 度快门
+}
+
+impl Timeline {
+    // 第 frame 帧快门打开和关闭的时间
+    pub fn shutter_interval(&self, frame: u32) -> (f64, f64) {
+        let open = frame as f64 / self.fps;
+        (open, open + self.shutter / self.fps)
+    }
+}
+
+// 相机的关键帧；相机在一帧之内取快门中间时刻的位置，不产生相机的运动模糊
+#[derive(Clone)]
+pub struct CameraAnimation {
+    pub position: Track<Vec3>,
+    pub look_at: Track<Vec3>,
+    pub vup: Vec3,
+    pub fov: Track<f64>, // 垂直视角，单位为度
+    pub aperture: Track<f64>,
+    pub focus_dist: Track<f64>,
+}
+
+impl CameraAnimation {
+    // 快门时间为 [time0, time1] 的相机，物体在这段时间内的运动形成运动模糊
+    pub fn camera(&self, time0: f64, time1: f64, aspect: f64) -> Camera {
+        let time = (time0 + time1) / 2.;
+        Camera::new(
+            self.position.sample(time),
+            self.look_at.sample(time),
+            self.vup,
+            self.fov.sample(time),
+            aspect,
+            self.aperture.sample(time),
+            self.focus_dist.sample(time),
+            time0,
+            time1,
+        )
+    }
+}
+
+// 随时间变化的缩放、旋转和平移
+#[derive(Clone)]
+pub struct AnimatedTransform {
+    pub translation: Track<Vec3>,
+    pub rotation: Track<Quaternion>,
+    pub scale: Track<Vec3>,
+}
+
+impl AnimatedTransform {
+    pub fn new(translation: Track<Vec3>) -> Self {
+        Self {
+            translation,
+            rotation: Track::constant(Quaternion::identity()),
+            scale: Track::constant(Vec3::ones()),
+        }
+    }
+
+    pub fn at(&self, time: f64) -> Transform {
+        Transform {
+            translation: self.translation.sample(time),
+            rotation: self.rotation.sample(time).normalize(),
+            scale: self.scale.sample(time),
+        }
+    }
+
+    // 物体坐标下的包围盒 local 在 [t0, t1] 内扫过的范围
+    // 平移和缩放都落在各自关键帧与控制点的凸包里；旋转不变时逐个组合凸包的顶点，
+    // 旋转时用以原点为中心、包住所有缩放后顶点的球
+    pub fn bound(&self, local: AABB, t0: f64, t1: f64) -> AABB {
+        let mut corners = Vec::with_capacity(8);
+        for i in 0..8 {
+            corners.push(Vec3::new(
+                if i & 1 == 0 { local.min.x } else { local.max.x },
+                if i & 2 == 0 { local.min.y } else { local.max.y },
+                if i & 4 == 0 { local.min.z } else { local.max.z },
+            ));
+        }
+        let scales = self.scale.hull(t0, t1);
+        let rotations: Vec<Quaternion> = self
+            .rotation
+            .hull(t0, t1)
+            .iter()
+            .map(|q| q.normalize())
+            .collect();
+        let fixed = rotations
+            .iter()
+            .all(|q| q.dot(rotations[0]).abs() > 1. - 1e-12);
+
+        let mut object_box: Option<AABB> = None;
+        let mut include = |p: Vec3| {
+            object_box = Some(match object_box {
+                Some(b) => AABB::surrounding_box(b, AABB::new(p, p)),
+                None => AABB::new(p, p),
+            });
+        };
+        if fixed {
+            for s in &scales {
+                for corner in &corners {
+                    include(rotations[0].rotate(corner.elemul(*s)));
+                }
+            }
+        } else {
+            let radius = scales
+                .iter()
+                .flat_map(|s| corners.iter().map(move |c| c.elemul(*s).length()))
+                .fold(0., f64::max);
+            include(Vec3::new(radius, radius, radius));
+            include(-Vec3::new(radius, radius, radius));
+        }
+        let object_box = object_box.unwrap_or_default();
+
+        let mut output_box: Option<AABB> = None;
+        for offset in self.translation.hull(t0, t1) {
+            let moved = AABB::new(object_box.min + offset, object_box.max + offset);
+            output_box = Some(match output_box {
+                Some(b) => AABB::surrounding_box(b, moved),
+                None => moved,
+            });
+        }
+        output_box.unwrap_or(object_box)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utility::RandGen;
+    use rand::{Rng, SeedableRng};
+    use track::Interpolation::{self, Bezier, Linear, Step};
+
+    const KINDS: [Interpolation; 3] = [Step, Linear, Bezier];
+
+    fn random_track<T: track::Animatable>(
+        rng: &mut RandGen,
+        mut value: impl FnMut(&mut RandGen) -> T,
+    ) -> Track<T> {
+        let mut time = rng.gen_range(-1.0..0.5);
+        let first = value(rng);
+        let mut track = Track::new(time, first, KINDS[rng.gen_range(0..3)]);
+        for _ in 0..rng.gen_range(0..4) {
+            time += rng.gen_range(0.1..1.0);
+            let v = value(rng);
+            track = track.key(time, v, KINDS[rng.gen_range(0..3)]);
+        }
+        track
+    }
+
+    fn inside(b: &AABB, p: Vec3) -> bool {
+        (0..3).all(|i| p[i] >= b.min[i] - 1e-9 && p[i] <= b.max[i] + 1e-9)
+    }
+
+    // 随机的平移、旋转、缩放轨道下，物体包围盒的顶点在 [t0, t1] 内的任意时刻都在 bound 给出的盒子里；
+    // 每个时刻的变换是仿射的，顶点在盒子里则整个物体都在
+    #[test]
+    fn bound_contains_the_motion() {
+        let mut rng = RandGen::seed_from_u64(3);
+        for _ in 0..300 {
+            let transform = AnimatedTransform {
+                translation: random_track(&mut rng, |rng| Vec3::random(rng, -3., 3.)),
+                rotation: if rng.gen_bool(0.3) {
+                    Track::constant(Quaternion::from_axis_angle(
+                        Vec3::random(&mut rng, -1., 1.),
+                        rng.gen_range(-180.0..180.0),
+                    ))
+                } else {
+                    random_track(&mut rng, |rng| {
+                        Quaternion::from_axis_angle(
+                            Vec3::random(rng, -1., 1.),
+                            rng.gen_range(-180.0..180.0),
+                        )
+                    })
+                },
+                scale: random_track(&mut rng, |rng| Vec3::random(rng, 0.2, 2.)),
+            };
+            let min = Vec3::random(&mut rng, -2., 1.);
+            let local = AABB::new(min, min + Vec3::random(&mut rng, 0.1, 2.));
+            let t0 = rng.gen_range(-1.5..2.0);
+            let t1 = t0 + rng.gen_range(0.0..1.5);
+            let bound = transform.bound(local, t0, t1);
+            for i in 0..=50 {
+                let m = transform.at(t0 + (t1 - t0) * i as f64 / 50.);
+                for corner in 0..8 {
+                    let p = Vec3::new(
+                        if corner & 1 == 0 {
+                            local.min.x
+                        } else {
+                            local.max.x
+                        },
+                        if corner & 2 == 0 {
+                            local.min.y
+                        } else {
+                            local.max.y
+                        },
+                        if corner & 4 == 0 {
+                            local.min.z
+                        } else {
+                            local.max.z
+                        },
+                    );
+                    assert!(inside(&bound, m.point(p)));
+                }
+            }
+        }
+    }
+}
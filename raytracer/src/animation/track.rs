@@ -0,0 +1,254 @@
+use crate::basic::{quaternion::Quaternion, vec::Vec3};
+
+// 可以插值的量；add 和 scale 用于计算 Bezier 的控制点
+pub trait Animatable: Copy {
+    fn add(self, other: Self) -> Self;
+    fn scale(self, s: f64) -> Self;
+
+    // 从 self 到 other 的插值，s 在 [0, 1] 内
+    fn interpolate(self, other: Self, s: f64) -> Self {
+        self.scale(1. - s).add(other.scale(s))
+    }
+}
+
+impl Animatable for f64 {
+    fn add(self, other: Self) -> Self {
+        self + other
+    }
+
+    fn scale(self, s: f64) -> Self {
+        self * s
+    }
+}
+
+impl Animatable for Vec3 {
+    fn add(self, other: Self) -> Self {
+        self + other
+    }
+
+    fn scale(self, s: f64) -> Self {
+        self * s
+    }
+}
+
+// 旋转用球面线性插值，Bezier 曲线也由一层层的 slerp 构成
+impl Animatable for Quaternion {
+    fn add(self, other: Self) -> Self {
+        Quaternion::new(
+            self.w + other.w,
+            self.x + other.x,
+            self.y + other.y,
+            self.z + other.z,
+        )
+    }
+
+    fn scale(self, s: f64) -> Self {
+        Quaternion::new(self.w * s, self.x * s, self.y * s, self.z * s)
+    }
+
+    fn interpolate(self, other: Self, s: f64) -> Self {
+        self.slerp(other, s)
+    }
+}
+
+// 从这个关键帧到下一个关键帧之间的插值方式
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Interpolation {
+    Step, // 保持不变，到下一帧时跳变
+    Linear,
+    // 三次 Bezier，控制点由相邻关键帧自动给出（Catmull-Rom 切线），首尾两帧切线为 0，即缓入缓出
+    Bezier,
+}
+
+#[derive(Clone, Copy)]
+pub struct Keyframe<T> {
+    pub time: f64,
+    pub value: T,
+    pub interpolation: Interpolation,
+}
+
+// 按时间排好序的关键帧，至少有一帧；第一帧之前和最后一帧之后保持不变
+#[derive(Clone)]
+pub struct Track<T> {
+    pub keys: Vec<Keyframe<T>>,
+}
+
+impl<T: Animatable> Track<T> {
+    // 之后的关键帧可以连着写：Track::new(0., a, Linear).key(1., b, Linear)
+    pub fn new(time: f64, value: T, interpolation: Interpolation) -> Self {
+        Self {
+            keys: vec![Keyframe {
+                time,
+                value,
+                interpolation,
+            }],
+        }
+    }
+
+    pub fn constant(value: T) -> Self {
+        Track::new(0., value, Interpolation::Step)
+    }
+
+    pub fn key(mut self, time: f64, value: T, interpolation: Interpolation) -> Self {
+        let index = self.keys.iter().take_while(|k| k.time <= time).count();
+        self.keys.insert(
+            index,
+            Keyframe {
+                time,
+                value,
+                interpolation,
+            },
+        );
+        self
+    }
+
+    // 时间 time 所在的区间 [keys[i], keys[i + 1]]，在所有关键帧之外时返回 None
+    fn segment(&self, time: f64) -> Option<usize> {
+        let last = self.keys.len().checked_sub(1)?;
+        if last == 0 || time <= self.keys[0].time || time >= self.keys[last].time {
+            return None;
+        }
+        Some(self.keys.iter().take_while(|k| k.time <= time).count() - 1)
+    }
+
+    // 区间 i 的 Bezier 控制点 (p0, c0, c1, p1)
+    fn control_points(&self, i: usize) -> (T, T, T, T) {
+        let k0 = &self.keys[i];
+        let k1 = &self.keys[i + 1];
+        let dt = k1.time - k0.time;
+        // 切线乘上区间长度的三分之一，时间与曲线参数保持线性关系
+        let tangent = |j: usize| -> T {
+            if j == 0 || j + 1 == self.keys.len() {
+                return self.keys[j].value.scale(0.);
+            }
+            let prev = &self.keys[j - 1];
+            let next = &self.keys[j + 1];
+            next.value
+                .add(prev.value.scale(-1.))
+                .scale(1. / (next.time - prev.time))
+        };
+        (
+            k0.value,
+            k0.value.add(tangent(i).scale(dt / 3.)),
+            k1.value.add(tangent(i + 1).scale(-dt / 3.)),
+            k1.value,
+        )
+    }
+
+    pub fn sample(&self, time: f64) -> T {
+        let i = match self.segment(time) {
+            Some(i) => i,
+            None if time <= self.keys[0].time => return self.keys[0].value,
+            None => return self.keys[self.keys.len() - 1].value,
+        };
+        let k0 = &self.keys[i];
+        let k1 = &self.keys[i + 1];
+        let s = (time - k0.time) / (k1.time - k0.time);
+        match k0.interpolation {
+            Interpolation::Step => k0.value,
+            Interpolation::Linear => k0.value.interpolate(k1.value, s),
+            // de Casteljau 算法
+            Interpolation::Bezier => {
+                let (p0, c0, c1, p1) = self.control_points(i);
+                let p01 = p0.interpolate(c0, s);
+                let p12 = c0.interpolate(c1, s);
+                let p23 = c1.interpolate(p1, s);
+                let p012 = p01.interpolate(p12, s);
+                let p123 = p12.interpolate(p23, s);
+                p012.interpolate(p123, s)
+            }
+        }
+    }
+
+    // [t0, t1] 内取到的值一定落在返回的这些点的凸包里：两端的值、区间内的关键帧和 Bezier 控制点
+    pub fn hull(&self, t0: f64, t1: f64) -> Vec<T> {
+        let mut points = vec![self.sample(t0), self.sample(t1)];
+        for i in 0..self.keys.len().saturating_sub(1) {
+            let k0 = &self.keys[i];
+            let k1 = &self.keys[i + 1];
+            if k1.time < t0 || k0.time > t1 {
+                continue;
+            }
+            points.push(k0.value);
+            points.push(k1.value);
+            if k0.interpolation == Interpolation::Bezier {
+                let (_, c0, c1, _) = self.control_points(i);
+                points.push(c0);
+                points.push(c1);
+            }
+        }
+        points
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utility::RandGen;
+    use rand::{Rng, SeedableRng};
+    use Interpolation::{Bezier, Linear, Step};
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-12
+    }
+
+    #[test]
+    fn values_hold_outside_and_hit_the_keys() {
+        for &interpolation in &[Step, Linear, Bezier] {
+            let track = Track::new(1., 2., interpolation)
+                .key(2., -1., interpolation)
+                .key(4., 3., interpolation);
+            assert!(close(track.sample(-5.), 2.));
+            assert!(close(track.sample(1.), 2.));
+            assert!(close(track.sample(2.), -1.));
+            assert!(close(track.sample(4.), 3.));
+            assert!(close(track.sample(10.), 3.));
+        }
+    }
+
+    #[test]
+    fn step_and_linear_values() {
+        let step = Track::new(0., 1., Step).key(2., 5., Step);
+        assert!(close(step.sample(1.9), 1.));
+        let linear = Track::new(0., 1., Linear).key(2., 5., Linear);
+        assert!(close(linear.sample(0.5), 2.));
+        assert!(close(linear.sample(1.), 3.));
+    }
+
+    #[test]
+    fn bezier_values() {
+        // 两个关键帧时两端切线为 0，曲线是 3s² - 2s³
+        let ease = Track::new(0., 0., Bezier).key(1., 1., Bezier);
+        assert!(close(ease.sample(0.25), 0.15625));
+        assert!(close(ease.sample(0.5), 0.5));
+        // 中间关键帧的切线为 (5 - 0) / (3 - 0)，第一段的控制点为 0、0、1 - 5/9、1
+        let track = Track::new(0., 0., Bezier)
+            .key(1., 1., Bezier)
+            .key(3., 5., Bezier);
+        assert!(close(track.sample(0.5), 7. / 24.));
+    }
+
+    // 随机生成的轨道上，[t0, t1] 内采样的每个值都落在 hull 的范围里
+    #[test]
+    fn samples_stay_inside_the_hull() {
+        let mut rng = RandGen::seed_from_u64(11);
+        let kinds = [Step, Linear, Bezier];
+        for _ in 0..200 {
+            let mut time = rng.gen_range(-1.0..1.0);
+            let mut track = Track::new(time, rng.gen_range(-5.0..5.0), kinds[rng.gen_range(0..3)]);
+            for _ in 0..rng.gen_range(0..5) {
+                time += rng.gen_range(0.1..2.0);
+                track = track.key(time, rng.gen_range(-5.0..5.0), kinds[rng.gen_range(0..3)]);
+            }
+            let t0 = rng.gen_range(-2.0..time + 1.);
+            let t1 = t0 + rng.gen_range(0.0..3.0);
+            let hull = track.hull(t0, t1);
+            let min = hull.iter().cloned().fold(f64::INFINITY, f64::min);
+            let max = hull.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            for i in 0..=100 {
+                let value = track.sample(t0 + (t1 - t0) * i as f64 / 100.);
+                assert!(value >= min - 1e-9 && value <= max + 1e-9);
+            }
+        }
+    }
+}
@@ -1,5 +1,9 @@
 use crate::{
-    basic::{ray::Ray, vec::Vec3},
+    basic::{
+        ray::{Ray, RayDifferential},
+        vec::Vec3,
+    },
+    sampler::Sampler,
     utility,
 };
 
@@ -49,15 +53,54 @@ impl Camera {
         }
     }
 
-    pub fn get_ray(self, s: f64, t: f64) -> Ray {
-        let rd = Vec3::random_in_unit_disk() * self.lens_radius;
+    pub fn get_ray(self, s: f64, t: f64, sampler: &mut dyn Sampler) -> Ray {
+        let rd = Vec3::unit_disk_from_square(sampler.get_2d()) * self.lens_radius;
         let offset = self.u * rd.x + self.v * rd.y;
         Ray {
             dir: (self.lower_left_corner + self.horizontal * s + self.vertical * t
                 - self.origin
                 - offset),
             orig: (self.origin + offset),
-            time: utility::random_double(self.time0, self.time1),
+            time: self.time0 + sampler.get_1d() * (self.time1 - self.time0),
+            differential: None,
         }
     }
+
+    // 同时生成相邻像素 (s + ds, t) 和 (s, t + dt) 的光线，它们与主光线共用镜头上的采样点
+    pub fn get_ray_differential(
+        self,
+        s: f64,
+        t: f64,
+        ds: f64,
+        dt: f64,
+        sampler: &mut dyn Sampler,
+    ) -> Ray {
+        let mut ray = self.get_ray(s, t, sampler);
+        let offset = ray.orig - self.origin;
+        let target = |s: f64, t: f64| {
+            self.lower_left_corner + self.horizontal * s + self.vertical * t - self.origin - offset
+        };
+        ray.differential = Some(RayDifferential {
+            rx_orig: ray.orig,
+            rx_dir: target(s + ds, t),
+            ry_orig: ray.orig,
+            ry_dir: target(s, t + dt),
+        });
+        ray
+    }
+
+    // 把世界空间中的点投影到成像平面，返回 (s, t)，点在相机背后时返回 None
+    pub fn project(self, point: Vec3) -> Option<(f64, f64)> {
+        let dir = point - self.origin;
+        let denom = dir * self.w;
+        if denom >= 0. {
+            return None;
+        }
+        let k = (self.lower_left_corner - self.origin) * self.w / denom;
+        let q = self.origin + dir * k - self.lower_left_corner;
+        Some((
+            q * self.horizontal / self.horizontal.squared_length(),
+            q * self.vertical / self.vertical.squared_length(),
+        ))
+    }
 }
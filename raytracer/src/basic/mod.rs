@@ -1,3 +1,5 @@
 pub mod camera;
+pub mod quaternion;
 pub mod ray;
+pub mod transform;
 pub mod vec;
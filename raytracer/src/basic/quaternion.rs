@@ -0,0 +1,157 @@
+use std::ops::Mul;
+
+use crate::{basic::vec::Vec3, utility};
+
+// 表示旋转的单位四元数 w + xi + yj + zk
+#[derive(Copy, Clone, PartialEq)]
+pub struct Quaternion {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Quaternion::identity()
+    }
+}
+
+impl Quaternion {
+    pub fn new(w: f64, x: f64, y: f64, z: f64) -> Self {
+        Self { w, x, y, z }
+    }
+
+    pub fn identity() -> Self {
+        Quaternion::new(1., 0., 0., 0.)
+    }
+
+    // 绕 axis 按右手定则转 degrees 度
+    pub fn from_axis_angle(axis: Vec3, degrees: f64) -> Self {
+        let (sin, cos) = (utility::degree_to_radian(degrees) / 2.).sin_cos();
+        let axis = axis.unit() * sin;
+        Quaternion::new(cos, axis.x, axis.y, axis.z)
+    }
+
+    pub fn dot(self, other: Self) -> f64 {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn normalize(self) -> Self {
+        let length = self.dot(self).sqrt();
+        if length == 0. {
+            return Quaternion::identity();
+        }
+        Quaternion::new(
+            self.w / length,
+            self.x / length,
+            self.y / length,
+            self.z / length,
+        )
+    }
+
+    pub fn conjugate(self) -> Self {
+        Quaternion::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    // 旋转向量 v，要求是单位四元数
+    pub fn rotate(self, v: Vec3) -> Vec3 {
+        let u = Vec3::new(self.x, self.y, self.z);
+        let t = Vec3::cross(u, v) * 2.;
+        v + t * self.w + Vec3::cross(u, t)
+    }
+
+    // 球面线性插值，沿最短的路径转过去，角速度不变
+    pub fn slerp(self, other: Self, t: f64) -> Self {
+        let a = self.normalize();
+        let mut b = other.normalize();
+        let mut cos = a.dot(b);
+        // q 和 -q 是同一个旋转，取夹角小于 90 度的那个
+        if cos < 0. {
+            b = Quaternion::new(-b.w, -b.x, -b.y, -b.z);
+            cos = -cos;
+        }
+        let (wa, wb) = if cos > 0.9995 {
+            // 几乎重合时 sin 很小，退化为线性插值
+            (1. - t, t)
+        } else {
+            let theta = cos.acos();
+            let sin = theta.sin();
+            (((1. - t) * theta).sin() / sin, (t * theta).sin() / sin)
+        };
+        Quaternion::new(
+            a.w * wa + b.w * wb,
+            a.x * wa + b.x * wb,
+            a.y * wa + b.y * wb,
+            a.z * wa + b.z * wb,
+        )
+        .normalize()
+    }
+}
+
+// a * b 表示先做 b 的旋转，再做 a 的旋转
+impl Mul for Quaternion {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Quaternion::new(
+            self.w * other.w - self.x * other.x - self.y * other.y - self.z * other.z,
+            self.w * other.x + self.x * other.w + self.y * other.z - self.z * other.y,
+            self.w * other.y - self.x * other.z + self.y * other.w + self.z * other.x,
+            self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Vec3, b: Vec3) -> bool {
+        (a - b).length() < 1e-9
+    }
+
+    #[test]
+    fn rotate_follows_the_right_hand_rule() {
+        let x = Vec3::new(1., 0., 0.);
+        let quarter_y = Quaternion::from_axis_angle(Vec3::new(0., 1., 0.), 90.);
+        assert!(close(quarter_y.rotate(x), Vec3::new(0., 0., -1.)));
+        let quarter_z = Quaternion::from_axis_angle(Vec3::new(0., 0., 1.), 90.);
+        assert!(close(quarter_z.rotate(x), Vec3::new(0., 1., 0.)));
+        // a * b 先转 b 再转 a
+        let v = Vec3::new(0.3, -1.2, 2.);
+        assert!(close(
+            (quarter_z * quarter_y).rotate(v),
+            quarter_z.rotate(quarter_y.rotate(v))
+        ));
+        assert!(close(quarter_z.conjugate().rotate(quarter_z.rotate(v)), v));
+    }
+
+    #[test]
+    fn slerp_turns_at_constant_speed() {
+        let x = Vec3::new(1., 0., 0.);
+        let z = Vec3::new(0., 0., 1.);
+        let a = Quaternion::identity();
+        let b = Quaternion::from_axis_angle(z, 120.);
+        assert!(close(a.slerp(b, 0.).rotate(x), x));
+        assert!(close(a.slerp(b, 1.).rotate(x), b.rotate(x)));
+        for &t in &[0.25, 0.5, 0.75] {
+            let expected = Quaternion::from_axis_angle(z, 120. * t);
+            assert!(close(a.slerp(b, t).rotate(x), expected.rotate(x)));
+        }
+    }
+
+    #[test]
+    fn slerp_takes_the_shortest_path() {
+        let x = Vec3::new(1., 0., 0.);
+        let z = Vec3::new(0., 0., 1.);
+        let b = Quaternion::from_axis_angle(z, 90.);
+        // -b 与 b 是同一个旋转，中点仍然是转 45 度而不是反方向转 135 度
+        let negated = Quaternion::new(-b.w, -b.x, -b.y, -b.z);
+        let half = Quaternion::identity().slerp(negated, 0.5);
+        assert!(close(
+            half.rotate(x),
+            Quaternion::from_axis_angle(z, 45.).rotate(x)
+        ));
+    }
+}
@@ -1,35 +1,62 @@
+use crate::sampler::Sampler;
 use crate::{basic::vec::Vec3, hittable::Hittable};
 use std::f64::INFINITY;
+// 相邻像素 x、y 方向上的两条光线，用于估计纹理的滤波宽度
+#[derive(Copy, Clone, Default)]
+pub struct RayDifferential {
+    pub rx_orig: Vec3,
+    pub rx_dir: Vec3,
+    pub ry_orig: Vec3,
+    pub ry_dir: Vec3,
+}
+
 #[derive(Copy, Clone, Default)]
 pub struct Ray {
     pub dir: Vec3,  //方向
     pub orig: Vec3, //原点
     pub time: f64,  //时间
+    pub differential: Option<RayDifferential>,
 }
 
 impl Ray {
     pub fn new(orig: Vec3, dir: Vec3, time: f64) -> Self {
-        Self { dir, orig, time }
+        Self {
+            dir,
+            orig,
+            time,
+            differential: None,
+        }
     }
 
     pub fn at(&self, t: f64) -> Vec3 {
         self.orig + self.dir * t
     }
+
+    // 每个像素采样多次时，每个样本只负责像素的一部分，相应缩小足迹
+    pub fn scale_differentials(&mut self, s: f64) {
+        if let Some(d) = self.differential.as_mut() {
+            d.rx_orig = self.orig + (d.rx_orig - self.orig) * s;
+            d.ry_orig = self.orig + (d.ry_orig - self.orig) * s;
+            d.rx_dir = self.dir + (d.rx_dir - self.dir) * s;
+            d.ry_dir = self.dir + (d.ry_dir - self.dir) * s;
+        }
+    }
 }
 
 impl Ray {
-    pub fn ray_color<T>(self, world: &T, depth: i32) -> Vec3
+    pub fn ray_color<T>(self, world: &T, depth: i32, sampler: &mut dyn Sampler) -> Vec3
     where
         T: Hittable + 'static,
     {
         if depth <= 0 {
             return Vec3::new(0., 0., 0.);
         }
-        if let Some(tmp_rec) = world.hit(self, 0.001, INFINITY) {
-            if let Some(tmp_scatter) = tmp_rec.mat_ptr.scatter(self, tmp_rec.clone()) {
+        if let Some(mut tmp_rec) = world.hit(self, 0.001, INFINITY, sampler.rng()) {
+            tmp_rec.compute_footprint(&self);
+            if let Some(tmp_scatter) = tmp_rec.mat_ptr.scatter(self, tmp_rec.clone(), sampler) {
                 Vec3::elemul(
                     tmp_scatter.attenuation,
-                    Ray::ray_color(tmp_scatter.scattered, world, depth - 1),
+                    Ray::ray_color(tmp_scatter.scattered, world, depth - 1, sampler),
                 )
             } else {
                 Vec3::new(0., 0., 0.)
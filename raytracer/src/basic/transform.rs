@@ -0,0 +1,47 @@
+use crate::basic::{quaternion::Quaternion, vec::Vec3};
+
+// 物体坐标到世界坐标的变换：先按各轴缩放，再旋转，最后平移
+#[derive(Copy, Clone)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quaternion,
+    pub scale: Vec3, // 各分量不能为 0
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            translation: Vec3::zero(),
+            rotation: Quaternion::identity(),
+            scale: Vec3::ones(),
+        }
+    }
+}
+
+impl Transform {
+    pub fn point(&self, p: Vec3) -> Vec3 {
+        self.vector(p) + self.translation
+    }
+
+    pub fn vector(&self, v: Vec3) -> Vec3 {
+        self.rotation.rotate(v.elemul(self.scale))
+    }
+
+    // 法向量按逆转置变换，结果没有单位化
+    pub fn normal(&self, n: Vec3) -> Vec3 {
+        self.rotation.rotate(Vec3::new(
+            n.x / self.scale.x,
+            n.y / self.scale.y,
+            n.z / self.scale.z,
+        ))
+    }
+
+    pub fn inverse_point(&self, p: Vec3) -> Vec3 {
+        self.inverse_vector(p - self.translation)
+    }
+
+    pub fn inverse_vector(&self, v: Vec3) -> Vec3 {
+        let v = self.rotation.conjugate().rotate(v);
+        Vec3::new(v.x / self.scale.x, v.y / self.scale.y, v.z / self.scale.z)
+    }
+}
@@ -4,6 +4,8 @@ use std::ops::{
 };
 //Index,IndexMut []的赋值
 use crate::utility;
+use rand::Rng;
+use std::f64::consts::PI;
 
 #[derive(Clone, Copy, Default, PartialEq)]
 pub struct Vec3 {
@@ -54,28 +56,28 @@ impl Vec3 {
             z: op1.x * op2.y - op1.y * op2.x,
         }
     }
-    pub fn random(min: f64, max: f64) -> Self {
+    pub fn random<R: Rng + ?Sized>(rng: &mut R, min: f64, max: f64) -> Self {
         Self {
-            x: utility::random_double(min, max),
-            y: utility::random_double(min, max),
-            z: utility::random_double(min, max),
+            x: utility::random_double(rng, min, max),
+            y: utility::random_double(rng, min, max),
+            z: utility::random_double(rng, min, max),
         }
     }
 
-    pub fn random_in_unit_sphere() -> Self {
+    pub fn random_in_unit_sphere<R: Rng + ?Sized>(rng: &mut R) -> Self {
         loop {
-            let p = Vec3::random(-1., 1.);
+            let p = Vec3::random(rng, -1., 1.);
             if p.length() < 1. {
                 return p;
             }
         }
     }
 
-    pub fn random_in_unit_disk() -> Self {
+    pub fn random_in_unit_disk<R: Rng + ?Sized>(rng: &mut R) -> Self {
         loop {
             let p = Vec3::new(
-                utility::random_double(-1., 1.),
-                utility::random_double(-1., 1.),
+                utility::random_double(rng, -1., 1.),
+                utility::random_double(rng, -1., 1.),
                 0.,
             );
             if p.length() < 1. {
@@ -84,12 +86,12 @@ impl Vec3 {
         }
     }
 
-    pub fn random_unit_sphere() -> Self {
-        Vec3::unit(Vec3::random_in_unit_sphere())
+    pub fn random_unit_sphere<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Vec3::unit(Vec3::random_in_unit_sphere(rng))
     }
 
-    pub fn random_in_hemisphere(normal: Vec3) -> Vec3 {
-        let in_unit_sphere = Vec3::random_in_unit_sphere();
+    pub fn random_in_hemisphere<R: Rng + ?Sized>(normal: Vec3, rng: &mut R) -> Vec3 {
+        let in_unit_sphere = Vec3::random_in_unit_sphere(rng);
         if in_unit_sphere * normal > 0. {
             //和法线在同一个半球
             in_unit_sphere
@@ -98,6 +100,29 @@ impl Vec3 {
         }
     }
 
+    // 把 [0, 1)^2 中的样本均匀映射到单位球面上，供采样器使用
+    pub fn unit_sphere_from_square(u: (f64, f64)) -> Self {
+        let z = 1. - 2. * u.0;
+        let r = utility::fmax(1. - z * z, 0.).sqrt();
+        let phi = 2. * PI * u.1;
+        Vec3::new(r * phi.cos(), r * phi.sin(), z)
+    }
+
+    // 同心映射，把 [0, 1)^2 中的样本均匀且保持分层地映射到单位圆盘上
+    pub fn unit_disk_from_square(u: (f64, f64)) -> Self {
+        let a = 2. * u.0 - 1.;
+        let b = 2. * u.1 - 1.;
+        if a == 0. && b == 0. {
+            return Vec3::zero();
+        }
+        let (r, theta) = if a.abs() > b.abs() {
+            (a, PI / 4. * (b / a))
+        } else {
+            (b, PI / 2. - PI / 4. * (a / b))
+        };
+        Vec3::new(r * theta.cos(), r * theta.sin(), 0.)
+    }
+
     pub fn reflect(v: Self, n: Self) -> Self {
         v - n * (v * n) * 2.
     }
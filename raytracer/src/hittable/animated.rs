@@ -0,0 +1,56 @@
+use rand::RngCore;
+use std::rc::Rc;
+
+use crate::{
+    animation::AnimatedTransform,
+    basic::ray::Ray,
+    hittable::{HitRecord, Hittable},
+    material::Material,
+    optimization::aabb::AABB,
+};
+
+// 按关键帧缩放、旋转、平移的物体，变换在 Ray::time 时刻取值，快门时间内的运动形成运动模糊
+pub struct Animated {
+    pub object: Rc<dyn Hittable>,
+    pub transform: AnimatedTransform,
+}
+
+impl Hittable for Animated {
+    fn hit(&self, ray: Ray, t_min: f64, t_max: f64, rng: &mut dyn RngCore) -> Option<HitRecord> {
+        // 把光线变换到物体坐标里求交，方向不单位化，t 保持不变；再把交点变换回来
+        let m = self.transform.at(ray.time);
+        let mut local = ray;
+        local.orig = m.inverse_point(ray.orig);
+        local.dir = m.inverse_vector(ray.dir);
+        if let Some(d) = local.differential.as_mut() {
+            d.rx_orig = m.inverse_point(d.rx_orig);
+            d.ry_orig = m.inverse_point(d.ry_orig);
+            d.rx_dir = m.inverse_vector(d.rx_dir);
+            d.ry_dir = m.inverse_vector(d.ry_dir);
+        }
+        let mut rec = self.object.hit(local, t_min, t_max, rng)?;
+        // rec.local_p 保持子物体给出的物体坐标，纹理跟着物体一起运动
+        let local_p = rec.p;
+        rec.p = m.point(local_p);
+        rec.normal = m.normal(rec.normal).unit();
+        rec.geometric_normal = m.normal(rec.geometric_normal).unit();
+        rec.dpdu = m.vector(rec.dpdu);
+        rec.dpdv = m.vector(rec.dpdv);
+
+        // 交点的速度：物体自身的运动加上变换带来的运动，后者用中心差分
+        const H: f64 = 1e-4;
+        let p1 = self.transform.at(ray.time + H).point(local_p);
+        let p0 = self.transform.at(ray.time - H).point(local_p);
+        rec.velocity = m.vector(rec.velocity) + (p1 - p0) / (2. * H);
+        Some(rec)
+    }
+
+    fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB> {
+        let local = self.object.bounding_box(t0, t1)?;
+        Some(self.transform.bound(local, t0, t1))
+    }
+
+    fn materials(&self, list: &mut Vec<Rc<dyn Material>>) {
+        self.object.materials(list);
+    }
+}
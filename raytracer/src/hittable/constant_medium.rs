@@ -0,0 +1,118 @@
+use rand::RngCore;
+use std::rc::Rc;
+
+use crate::{
+    basic::{ray::Ray, vec::Vec3},
+    hittable::{HitRecord, Hittable},
+    material::{isotropic::Isotropic, Material},
+    optimization::aabb::AABB,
+    texture::Texture,
+    utility,
+};
+
+// 密度均匀的参与介质（烟、雾），边界本身不可见
+pub struct ConstantMedium {
+    pub boundary: Rc<dyn Hittable>,
+    pub phase_function: Rc<dyn Material>,
+    pub neg_inv_density: f64,
+}
+
+impl ConstantMedium {
+    pub fn new(boundary: Rc<dyn Hittable>, density: f64, albedo: Rc<dyn Texture>) -> Self {
+        Self {
+            boundary,
+            phase_function: Rc::new(Isotropic { albedo }),
+            neg_inv_density: -1. / density,
+        }
+    }
+
+    // 按指数分布采样光线在介质中走过的距离
+    pub fn sample_distance(neg_inv_density: f64, rng: &mut dyn RngCore) -> f64 {
+        neg_inv_density * (1. - utility::random_double(rng, 0., 1.)).ln()
+    }
+
+    pub fn medium_record(ray: Ray, t: f64, phase_function: &Rc<dyn Material>) -> HitRecord {
+        HitRecord {
+            p: ray.at(t),
+            normal: Vec3::new(1., 0., 0.), // 任意值
+            geometric_normal: Vec3::new(1., 0., 0.),
+            t,
+            front_face: true,
+            mat_ptr: phase_function.clone(),
+            u: 0.,
+            v: 0.,
+            dpdu: Vec3::zero(),
+            dpdv: Vec3::zero(),
+            footprint: Default::default(),
+            object_id: 0,
+            velocity: Vec3::zero(),
+            local_p: ray.at(t),
+        }
+    }
+}
+
+impl Hittable for ConstantMedium {
+    fn hit(&self, ray: Ray, t_min: f64, t_max: f64, rng: &mut dyn RngCore) -> Option<HitRecord> {
+        let mut rec1 = self.boundary.hit(ray, -f64::INFINITY, f64::INFINITY, rng)?;
+        let mut rec2 = self
+            .boundary
+            .hit(ray, rec1.t + 0.0001, f64::INFINITY, rng)?;
+        rec1.t = utility::fmax(rec1.t, t_min);
+        rec2.t = utility::fmin(rec2.t, t_max);
+        if rec1.t >= rec2.t {
+            return None;
+        }
+        rec1.t = utility::fmax(rec1.t, 0.);
+
+        let ray_length = ray.dir.length();
+        let distance_inside_boundary = (rec2.t - rec1.t) * ray_length;
+        let hit_distance = ConstantMedium::sample_distance(self.neg_inv_density, rng);
+        if hit_distance > distance_inside_boundary {
+            return None;
+        }
+        let t = rec1.t + hit_distance / ray_length;
+        Some(ConstantMedium::medium_record(ray, t, &self.phase_function))
+    }
+
+    fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB> {
+        self.boundary.bounding_box(t0, t1)
+    }
+
+    // 边界本身不可见，交点只会带相函数
+    fn materials(&self, list: &mut Vec<Rc<dyn Material>>) {
+        list.push(self.phase_function.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hittable::sphere::Sphere, texture::solid_color::SolidColor, utility::RandGen};
+    use rand::SeedableRng;
+
+    // 穿过介质而不散射的比例按 Beer 定律随厚度指数衰减，散射点都在边界以内
+    #[test]
+    fn transmittance_follows_beer_law() {
+        let albedo = Rc::new(SolidColor::new(0.5, 0.5, 0.5));
+        let boundary = Rc::new(Sphere {
+            center: Vec3::zero(),
+            r: 1.,
+            mat_ptr: Rc::new(Isotropic {
+                albedo: albedo.clone(),
+            }),
+        });
+        let medium = ConstantMedium::new(boundary, 0.5, albedo);
+        let mut rng = RandGen::seed_from_u64(1);
+        // 沿直径穿过，介质内走 2 个单位长度
+        let ray = Ray::new(Vec3::new(0., 0., 3.), Vec3::new(0., 0., -2.), 0.);
+        let n = 20000;
+        let mut passed = 0;
+        for _ in 0..n {
+            match medium.hit(ray, 0.001, f64::INFINITY, &mut rng) {
+                Some(rec) => assert!(rec.t >= 1. && rec.t <= 2. && rec.p.length() <= 1.),
+                None => passed += 1,
+            }
+        }
+        assert!((passed as f64 / n as f64 - (-1f64).exp()).abs() < 0.01);
+    }
+}
@@ -1,20 +1,32 @@
+pub mod animated;
+pub mod constant_medium;
 pub mod sphere;
+pub mod subsurface;
 
+use rand::RngCore;
 use std::rc::Rc;
 
 use crate::basic::{ray::Ray, vec::Vec3};
 use crate::material::Material;
 use crate::optimization::aabb::AABB;
+use crate::texture::Footprint;
 
 #[derive(Clone)]
 pub struct HitRecord {
-    pub p: Vec3,      //碰撞点
-    pub normal: Vec3, //法向量
+    pub p: Vec3,                //碰撞点
+    pub normal: Vec3,           //着色法向量，可能被法线贴图扰动
+    pub geometric_normal: Vec3, //几何法向量，与 normal 同侧
     pub t: f64,
     pub front_face: bool,                   //方向是否为外侧
     pub mat_ptr: std::rc::Rc<dyn Material>, //材料
     pub u: f64,
     pub v: f64,
+    pub dpdu: Vec3, //交点对纹理坐标的偏导，即切向量
+    pub dpdv: Vec3,
+    pub footprint: Footprint, //像素在交点处的足迹，由光线微分计算
+    pub object_id: u32,       //物体编号，0 表示未编号，由 HittableList 按下标填写
+    pub velocity: Vec3,       //交点随物体运动的速度，用于输出运动矢量
+    pub local_p: Vec3, //物体坐标下的交点，纹理的空间坐标取它，物体运动时纹理随之移动；静止物体与 p 相同
 }
 
 impl HitRecord {
@@ -25,15 +37,90 @@ impl HitRecord {
         } else {
             self.normal = -outward_normal;
         }
+        self.geometric_normal = self.normal;
+    }
+
+    // 把相邻像素的光线与交点处的切平面求交，得到 dp/dx、dp/dy，再解出 du/dx 等
+    pub fn compute_footprint(&mut self, ray: &Ray) {
+        self.footprint = Default::default();
+        let d = match ray.differential {
+            Some(d) => d,
+            None => return,
+        };
+        let plane = self.normal * self.p;
+        let tx = (plane - self.normal * d.rx_orig) / (self.normal * d.rx_dir);
+        let ty = (plane - self.normal * d.ry_orig) / (self.normal * d.ry_dir);
+        if !tx.is_finite() || !ty.is_finite() {
+            return;
+        }
+        let dpdx = d.rx_orig + d.rx_dir * tx - self.p;
+        let dpdy = d.ry_orig + d.ry_dir * ty - self.p;
+
+        // dp = dpdu * du + dpdv * dv 是超定方程，丢掉法向分量最大的一维后求解
+        let (a0, a1) = if self.normal.x.abs() > self.normal.y.abs()
+            && self.normal.x.abs() > self.normal.z.abs()
+        {
+            (1, 2)
+        } else if self.normal.y.abs() > self.normal.z.abs() {
+            (0, 2)
+        } else {
+            (0, 1)
+        };
+        let solve = |b0: f64, b1: f64| -> Option<(f64, f64)> {
+            let det = self.dpdu[a0] * self.dpdv[a1] - self.dpdv[a0] * self.dpdu[a1];
+            if det.abs() < 1e-12 {
+                return None;
+            }
+            Some((
+                (self.dpdv[a1] * b0 - self.dpdv[a0] * b1) / det,
+                (self.dpdu[a0] * b1 - self.dpdu[a1] * b0) / det,
+            ))
+        };
+        let (dudx, dvdx) = solve(dpdx[a0], dpdx[a1]).unwrap_or((0., 0.));
+        let (dudy, dvdy) = solve(dpdy[a0], dpdy[a1]).unwrap_or((0., 0.));
+        self.footprint = Footprint {
+            dudx,
+            dudy,
+            dvdx,
+            dvdy,
+            dpdx,
+            dpdy,
+        };
+    }
+}
+// 给一个物体指定编号，BVH 用它保持与原列表相同的编号
+pub struct ObjectId {
+    pub object: Rc<dyn Hittable>,
+    pub id: u32,
+}
+
+impl Hittable for ObjectId {
+    fn hit(&self, ray: Ray, t_min: f64, t_max: f64, rng: &mut dyn RngCore) -> Option<HitRecord> {
+        let mut rec = self.object.hit(ray, t_min, t_max, rng)?;
+        if rec.object_id == 0 {
+            rec.object_id = self.id;
+        }
+        Some(rec)
+    }
+
+    fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB> {
+        self.object.bounding_box(t0, t1)
+    }
+
+    fn materials(&self, list: &mut Vec<Rc<dyn Material>>) {
+        self.object.materials(list);
     }
 }
+
 pub trait Hittable {
     //特性，用于实现继承
-    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64, rng: &mut dyn RngCore) -> Option<HitRecord>;
     //判断光线在 [t_min, t_max] 内是否碰到物体
     //优化，用 Option 是否为 None 来判断碰撞与否，同时包括返回值
     fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB>;
     // AABB 优化，判断光线是否撞到 大的 box
+    fn materials(&self, list: &mut Vec<Rc<dyn Material>>);
+    // 按场景里的顺序列出交点可能带的材质，AOV 按这个顺序给材质编号
 }
 
 #[derive(Default)]
@@ -56,12 +143,15 @@ impl HittableList {
 }
 
 impl Hittable for HittableList {
-    fn hit(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: Ray, t_min: f64, t_max: f64, rng: &mut dyn RngCore) -> Option<HitRecord> {
         let mut hit_rec = None;
         let mut closest_so_far = t_max;
-        for i in &self.objects {
-            if let Some(temp_rec) = i.hit(ray, t_min, closest_so_far) {
+        for (index, i) in self.objects.iter().enumerate() {
+            if let Some(mut temp_rec) = i.hit(ray, t_min, closest_so_far, rng) {
                 closest_so_far = temp_rec.t;
+                if temp_rec.object_id == 0 {
+                    temp_rec.object_id = index as u32 + 1;
+                }
                 hit_rec = Some(temp_rec);
             }
         }
@@ -93,4 +183,10 @@ impl Hittable for HittableList {
             Some(output_box)
         }
     }
+
+    fn materials(&self, list: &mut Vec<Rc<dyn Material>>) {
+        for object in &self.objects {
+            object.materials(list);
+        }
+    }
 }
@@ -4,8 +4,10 @@ use crate::{
     hittable::{HitRecord, Hittable},
     material::Material,
     optimization::aabb::AABB,
+    utility,
 };
-use std::rc::Rc;
+use rand::RngCore;
+use std::{f64::consts::PI, rc::Rc};
 
 #[derive(Clone)]
 pub struct Sphere {
@@ -22,8 +24,31 @@ pub struct Sphere {
 //     pub mat: T, //不保存指针，直接保存结构体
 // }
 
+impl Sphere {
+    // p 为单位球面上的点，u 为绕 y 轴的角度，v 为从 -y 到 +y 的角度，都归一化到 [0, 1]
+    pub fn get_sphere_uv(p: Vec3) -> (f64, f64) {
+        // 舍入误差可能让 |p.y| 略大于 1，acos 会得到 NaN
+        let theta = utility::clamp(-p.y, -1., 1.).acos();
+        let phi = (-p.z).atan2(p.x) + PI;
+        (phi / (2. * PI), theta / PI)
+    }
+
+    // 对上面参数化求偏导得到的 dp/du、dp/dv，p 为单位球面上的点
+    pub fn get_sphere_partials(p: Vec3, r: f64) -> (Vec3, Vec3) {
+        let dpdu = Vec3::new(p.z, 0., -p.x) * (2. * PI * r);
+        let sin_theta = (p.x * p.x + p.z * p.z).sqrt();
+        let dpdv = if sin_theta > 1e-8 {
+            Vec3::new(-p.x * p.y / sin_theta, sin_theta, -p.z * p.y / sin_theta) * (PI * r)
+        } else {
+            // 极点处没有确定的方向
+            Vec3::new(0., 0., PI * r)
+        };
+        (dpdu, dpdv)
+    }
+}
+
 impl Hittable for Sphere {
-    fn hit(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: Ray, t_min: f64, t_max: f64, _rng: &mut dyn RngCore) -> Option<HitRecord> {
         let oc = ray.orig - self.center;
         let a = ray.dir * ray.dir;
         let half_b = ray.dir * oc;
@@ -44,13 +69,26 @@ impl Hittable for Sphere {
                 t: root,
                 p: ray.at(root),
                 normal: Vec3::new(0., 0., 0.),
+                geometric_normal: Vec3::new(0., 0., 0.),
                 front_face: true,
                 mat_ptr: self.mat_ptr.clone(),
                 u: 0.,
                 v: 0.,
+                dpdu: Vec3::zero(),
+                dpdv: Vec3::zero(),
+                footprint: Default::default(),
+                object_id: 0,
+                velocity: Vec3::zero(),
+                local_p: ray.at(root),
             };
             let outward_normal = (rec.p - self.center) / self.r;
             rec.set_face_normal(ray, outward_normal);
+            let (u, v) = Sphere::get_sphere_uv(outward_normal);
+            rec.u = u;
+            rec.v = v;
+            let (dpdu, dpdv) = Sphere::get_sphere_partials(outward_normal, self.r);
+            rec.dpdu = dpdu;
+            rec.dpdv = dpdv;
 
             Option::Some(rec)
         }
@@ -62,6 +100,10 @@ impl Hittable for Sphere {
             max: self.center + Vec3::new(self.r, self.r, self.r),
         })
     }
+
+    fn materials(&self, list: &mut Vec<Rc<dyn Material>>) {
+        list.push(self.mat_ptr.clone());
+    }
 }
 
 #[derive(Clone)]
@@ -75,13 +117,24 @@ pub struct MovingSphere {
 }
 
 impl MovingSphere {
+    // time0 与 time1 相同时没有运动区间，当作静止
     pub fn center(&self, time: f64) -> Vec3 {
+        if self.time1 == self.time0 {
+            return self.center0;
+        }
         self.center0
             + (self.center1 - self.center0) * (time - self.time0) / (self.time1 - self.time0)
     }
+
+    pub fn velocity(&self) -> Vec3 {
+        if self.time1 == self.time0 {
+            return Vec3::zero();
+        }
+        (self.center1 - self.center0) / (self.time1 - self.time0)
+    }
 }
 impl Hittable for MovingSphere {
-    fn hit(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: Ray, t_min: f64, t_max: f64, _rng: &mut dyn RngCore) -> Option<HitRecord> {
         let oc = ray.orig - MovingSphere::center(&self, ray.time);
         let a = ray.dir * ray.dir;
         let half_b = ray.dir * oc;
@@ -102,13 +155,27 @@ impl Hittable for MovingSphere {
                 t: root,
                 p: ray.at(root),
                 normal: Vec3::new(0., 0., 0.),
+                geometric_normal: Vec3::new(0., 0., 0.),
                 front_face: true,
                 mat_ptr: self.mat_ptr.clone(),
                 u: 0.,
                 v: 0.,
+                dpdu: Vec3::zero(),
+                dpdv: Vec3::zero(),
+                footprint: Default::default(),
+                object_id: 0,
+                velocity: self.velocity(),
+                // 换算到 time0 时刻球所在的位置
+                local_p: ray.at(root) - MovingSphere::center(&self, ray.time) + self.center0,
             };
             let outward_normal = (rec.p - MovingSphere::center(&self, ray.time)) / self.r;
             rec.set_face_normal(ray, outward_normal);
+            let (u, v) = Sphere::get_sphere_uv(outward_normal);
+            rec.u = u;
+            rec.v = v;
+            let (dpdu, dpdv) = Sphere::get_sphere_partials(outward_normal, self.r);
+            rec.dpdu = dpdu;
+            rec.dpdv = dpdv;
 
             Option::Some(rec)
         }
@@ -127,4 +194,40 @@ impl Hittable for MovingSphere {
 
         Some(AABB::surrounding_box(box0, box1))
     }
+
+    fn materials(&self, list: &mut Vec<Rc<dyn Material>>) {
+        list.push(self.mat_ptr.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uv_near(p: Vec3, u: f64, v: f64) -> bool {
+        let (pu, pv) = Sphere::get_sphere_uv(p);
+        (pu - u).abs() < 1e-9 && (pv - v).abs() < 1e-9
+    }
+
+    // v 在两极为 0 和 1，u 沿赤道绕一圈从 0 到 1
+    #[test]
+    fn sphere_uv_at_the_poles_and_equator() {
+        assert!(uv_near(Vec3::new(0., -1., 0.), 0.5, 0.));
+        assert!(uv_near(Vec3::new(0., 1., 0.), 0.5, 1.));
+        assert!(uv_near(Vec3::new(1., 0., 0.), 0.5, 0.5));
+        assert!(uv_near(Vec3::new(0., 0., 1.), 0.25, 0.5));
+        assert!(uv_near(Vec3::new(0., 0., -1.), 0.75, 0.5));
+        // 舍入误差让点略出球面时仍得到有效的 v
+        let (_, v) = Sphere::get_sphere_uv(Vec3::new(0., 1. + 1e-12, 0.));
+        assert!(v == 1.);
+    }
+
+    // 接缝在 -x 方向，两侧的 u 分别趋近 0 和 1
+    #[test]
+    fn sphere_uv_seam() {
+        let (before, _) = Sphere::get_sphere_uv(Vec3::unit(Vec3::new(-1., 0., -1e-6)));
+        let (after, _) = Sphere::get_sphere_uv(Vec3::unit(Vec3::new(-1., 0., 1e-6)));
+        assert!(before > 1. - 1e-6 && before <= 1.);
+        assert!(after >= 0. && after < 1e-6);
+    }
 }
@@ -0,0 +1,122 @@
+use rand::RngCore;
+use std::rc::Rc;
+
+use crate::{
+    basic::ray::Ray,
+    hittable::{constant_medium::ConstantMedium, HitRecord, Hittable},
+    material::{subsurface::Subsurface, Material},
+    optimization::aabb::AABB,
+};
+
+// 封闭物体内部的随机游走次表面散射
+// 光线在外面时与普通物体一样只看到边界，边界上的材质换成 Subsurface，负责折射进出
+// 光线在里面时按平均自由程采样散射点，走出边界前没有散射则交给边界处理折射或内部反射
+pub struct SubsurfaceVolume {
+    pub boundary: Rc<dyn Hittable>,
+    pub surface: Rc<dyn Material>,
+    pub phase_function: Rc<dyn Material>,
+    pub neg_inv_density: f64,
+}
+
+impl SubsurfaceVolume {
+    // boundary 自己的材质不会被用到，表面和内部的参数都来自 material
+    pub fn new(boundary: Rc<dyn Hittable>, material: Subsurface) -> Self {
+        Self {
+            boundary,
+            phase_function: Rc::new(material.phase_function()),
+            neg_inv_density: -material.mean_free_path,
+            surface: Rc::new(material),
+        }
+    }
+}
+
+impl Hittable for SubsurfaceVolume {
+    fn hit(&self, ray: Ray, t_min: f64, t_max: f64, rng: &mut dyn RngCore) -> Option<HitRecord> {
+        let mut exit = self.boundary.hit(ray, t_min, f64::INFINITY, rng)?;
+        exit.mat_ptr = self.surface.clone();
+        if exit.front_face {
+            // 光线从外面射来，命中的是边界外表面
+            return if exit.t <= t_max { Some(exit) } else { None };
+        }
+
+        // 下一个交点在边界背面，说明光线起点在物体内部
+        let hit_distance = ConstantMedium::sample_distance(self.neg_inv_density, rng);
+        let t = t_min + hit_distance / ray.dir.length();
+        if t < exit.t {
+            if t <= t_max {
+                Some(ConstantMedium::medium_record(ray, t, &self.phase_function))
+            } else {
+                None
+            }
+        } else if exit.t <= t_max {
+            Some(exit)
+        } else {
+            None
+        }
+    }
+
+    fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB> {
+        self.boundary.bounding_box(t0, t1)
+    }
+
+    // boundary 自己的材质被 surface 替换，不会出现在交点上
+    fn materials(&self, list: &mut Vec<Rc<dyn Material>>) {
+        list.push(self.surface.clone());
+        list.push(self.phase_function.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        basic::vec::Vec3, hittable::sphere::Sphere, texture::solid_color::SolidColor,
+        utility::RandGen,
+    };
+    use rand::SeedableRng;
+
+    fn wax() -> SubsurfaceVolume {
+        let material = Subsurface::new(1.4, Rc::new(SolidColor::new(0.9, 0.8, 0.7)), 0.5);
+        let boundary = Rc::new(Sphere {
+            center: Vec3::zero(),
+            r: 1.,
+            mat_ptr: Rc::new(material.clone()),
+        });
+        SubsurfaceVolume::new(boundary, material)
+    }
+
+    // 从外面看只有边界，交点带的是表面材质
+    #[test]
+    fn outside_rays_hit_the_surface() {
+        let volume = wax();
+        let ray = Ray::new(Vec3::new(0., 0., 3.), Vec3::new(0., 0., -1.), 0.);
+        let rec = volume
+            .hit(ray, 0.001, f64::INFINITY, &mut RandGen::seed_from_u64(0))
+            .unwrap();
+        assert!((rec.t - 2.).abs() < 1e-12 && rec.front_face);
+        assert!(Rc::ptr_eq(&rec.mat_ptr, &volume.surface));
+        assert!(volume
+            .hit(ray, 0.001, 1.5, &mut RandGen::seed_from_u64(0))
+            .is_none());
+    }
+
+    // 从里面出发时，在走出边界前散射的概率由平均自由程决定，散射点带相函数
+    #[test]
+    fn inside_rays_scatter_with_the_mean_free_path() {
+        let volume = wax();
+        let mut rng = RandGen::seed_from_u64(2);
+        let ray = Ray::new(Vec3::zero(), Vec3::new(0., 0., 1.), 0.);
+        let n = 20000;
+        let mut scattered = 0;
+        for _ in 0..n {
+            let rec = volume.hit(ray, 0., f64::INFINITY, &mut rng).unwrap();
+            if Rc::ptr_eq(&rec.mat_ptr, &volume.phase_function) {
+                assert!(rec.t < 1.);
+                scattered += 1;
+            } else {
+                assert!(Rc::ptr_eq(&rec.mat_ptr, &volume.surface) && !rec.front_face);
+            }
+        }
+        assert!((scattered as f64 / n as f64 - (1. - (-2f64).exp())).abs() < 0.01);
+    }
+}
@@ -1,35 +1,76 @@
+mod animation;
 mod basic;
 mod hittable;
 mod material;
 mod optimization;
+mod options;
+mod render;
+mod sampler;
 mod texture;
 mod utility;
 use crate::{
-    basic::{camera::Camera, ray::Ray, vec::Vec3},
-    hittable::{sphere, HittableList},
-    material::{dielectric::Dielectric, lambertian::Lambertian, metal::Metal},
+    animation::{
+        track::{Interpolation, Track},
+        AnimatedTransform, CameraAnimation, Timeline,
+    },
+    basic::{camera::Camera, quaternion::Quaternion, vec::Vec3},
+    hittable::{
+        animated::Animated, constant_medium::ConstantMedium, sphere, subsurface::SubsurfaceVolume,
+        HittableList,
+    },
+    material::{
+        bump_map::BumpMap, coated::Coated, cutout::Cutout, dielectric::Dielectric,
+        isotropic::Isotropic, lambertian::Lambertian, metal::Metal, mix::Mix,
+        normal_map::NormalMap, oren_nayar::OrenNayar, subsurface::Subsurface, thin_film::ThinFilm,
+        Material,
+    },
+    options::{Command, Options, Scene, USAGE},
+    render::{
+        aov, checkpoint,
+        denoise::{self, DenoiseSettings, Guides},
+        exr::{self, ExrChannel},
+        framebuffer::Framebuffer,
+        install_interrupt_handler,
+        output::{self, OutputFormat},
+        CropWindow, RenderSettings, Renderer, StopReason,
+    },
+    sampler::SamplerKind,
+    utility::RandGen,
 };
 
-use image::{ImageBuffer, RgbImage};
 use texture::{
-    checker::Checker,
+    checker::{Checker, CheckerMode},
+    image_texture::{ImageTexture, WrapMode},
+    math::{MixTexture, Multiply, Remap},
+    noise::{Fbm, RidgedMultifractal, Worley, WorleyMode},
+    pattern::{ColorRamp, Gradient, GradientKind, Marble, Wood},
     perlin::{NoiseTexture, Perlin},
     solid_color::SolidColor,
+    transform::TextureTransform,
 };
 
-use std::{fs::File, process::exit, rc::Rc};
+use std::{f64::consts::PI, path::Path, process::exit, rc::Rc};
+
+use rand::{Rng, SeedableRng};
 
 use console::style;
 use indicatif::{ProgressBar, ProgressStyle};
 
+// 图片宽度和宽高比，动画的每一帧也用这个大小
+const IMAGE_WIDTH: u32 = 400;
+const ASPECT_RATIO: f64 = 16.0 / 9.0;
+const HEATMAP_PATH: &str = "output/sample_count.png"; // 每个像素的样本数
+
 fn two_spheres() -> HittableList {
     let mut world: HittableList = Default::default();
     let checker = Rc::new(Checker {
         odd: Rc::new(SolidColor::new(0.2, 0.3, 0.1)),
         even: Rc::new(SolidColor::new(0.9, 0.9, 0.9)),
+        frequency: 10. / PI,
+        mode: CheckerMode::World,
     });
     let pertext = Rc::new(NoiseTexture {
-        noise: Perlin::new(),
+        noise: Perlin::new(0),
         scale: 4.,
     });
     let _mat1 = Rc::new(Lambertian { albedo: checker });
@@ -49,12 +90,87 @@ fn two_spheres() -> HittableList {
     world
 }
 
-pub fn random_scene() -> HittableList {
+// two_spheres 的动画版本：上面的球一边弹跳一边从后往前滚动，地面的噪声纹理逐渐变细
+// 物体的变换在每条光线的时间上取值，材质参数在每帧快门中间取一次
+fn animated_two_spheres(time0: f64, time1: f64) -> HittableList {
+    let mut world: HittableList = Default::default();
+    let scale = Track::new(0., 4., Interpolation::Linear).key(2., 10., Interpolation::Linear);
+    let pertext = Rc::new(NoiseTexture {
+        noise: Perlin::new(0),
+        scale: scale.sample((time0 + time1) / 2.),
+    });
+    let mat = Rc::new(Lambertian { albedo: pertext });
+
+    world.add(sphere::Sphere {
+        center: Vec3::new(0., -1000., 0.),
+        r: 1000.,
+        mat_ptr: mat,
+    });
+    let bounce = Track::new(0., Vec3::new(0., 2., -2.), Interpolation::Bezier)
+        .key(0.5, Vec3::new(0., 3.5, -1.), Interpolation::Bezier)
+        .key(1., Vec3::new(0., 2., 0.), Interpolation::Bezier)
+        .key(1.5, Vec3::new(0., 3.5, 1.), Interpolation::Bezier)
+        .key(2., Vec3::new(0., 2., 2.), Interpolation::Bezier);
+    // 每秒移动 2 个单位，半径为 2，滚过的角度约为每秒 57 度
+    let x_axis = Vec3::new(1., 0., 0.);
+    let roll = Track::new(
+        0.,
+        Quaternion::from_axis_angle(x_axis, 0.),
+        Interpolation::Linear,
+    )
+    .key(
+        1.,
+        Quaternion::from_axis_angle(x_axis, 57.3),
+        Interpolation::Linear,
+    )
+    .key(
+        2.,
+        Quaternion::from_axis_angle(x_axis, 114.6),
+        Interpolation::Linear,
+    );
+    let checker = Rc::new(Checker {
+        odd: Rc::new(SolidColor::new(0.2, 0.3, 0.1)),
+        even: Rc::new(SolidColor::new(0.9, 0.9, 0.9)),
+        frequency: 8.,
+        mode: CheckerMode::Uv,
+    });
+    world.add(Animated {
+        object: Rc::new(sphere::Sphere {
+            center: Vec3::zero(),
+            r: 2.,
+            mat_ptr: Rc::new(Lambertian { albedo: checker }),
+        }),
+        transform: AnimatedTransform {
+            rotation: roll,
+            ..AnimatedTransform::new(bounce)
+        },
+    });
+
+    world
+}
+
+// 相机绕场景转四分之一圈，同时稍微拉远
+fn two_spheres_camera() -> CameraAnimation {
+    CameraAnimation {
+        position: Track::new(0., Vec3::new(13., 2., 3.), Interpolation::Bezier)
+            .key(1., Vec3::new(10., 3., 9.), Interpolation::Bezier)
+            .key(2., Vec3::new(3., 2., 13.), Interpolation::Bezier),
+        look_at: Track::constant(Vec3::new(0., 1., 0.)),
+        vup: Vec3::new(0., 1., 0.),
+        fov: Track::new(0., 20., Interpolation::Linear).key(2., 26., Interpolation::Linear),
+        aperture: Track::constant(0.1),
+        focus_dist: Track::constant(10.),
+    }
+}
+
+pub fn random_scene<R: Rng + ?Sized>(rng: &mut R) -> HittableList {
     let mut world: HittableList = Default::default();
 
     let checker = Rc::new(Checker {
         odd: Rc::new(SolidColor::new(0.2, 0.3, 0.1)),
         even: Rc::new(SolidColor::new(0.9, 0.9, 0.9)),
+        frequency: 10. / PI,
+        mode: CheckerMode::World,
     }); //棋盘状的纹理
 
     let ground_material = Rc::new(Lambertian { albedo: checker });
@@ -66,23 +182,26 @@ pub fn random_scene() -> HittableList {
 
     for a in -11..11 {
         for b in -11..11 {
-            let choose_mat = utility::random_double(0., 1.);
+            let choose_mat = utility::random_double(rng, 0., 1.);
             let center = Vec3::new(
-                a as f64 + 0.9 * utility::random_double(0., 1.),
+                a as f64 + 0.9 * utility::random_double(rng, 0., 1.),
                 0.2,
-                b as f64 + 0.9 * utility::random_double(0., 1.),
+                b as f64 + 0.9 * utility::random_double(rng, 0., 1.),
             );
             if (center - Vec3::new(4., 0.2, 0.)).length() > 0.9 {
                 if choose_mat < 0.8 {
                     //diffuse
                     let sphere_material = Rc::new(Lambertian {
                         albedo: Rc::new(SolidColor {
-                            color_value: Vec3::elemul(Vec3::random(0., 1.), Vec3::random(0., 1.)),
+                            color_value: Vec3::elemul(
+                                Vec3::random(rng, 0., 1.),
+                                Vec3::random(rng, 0., 1.),
+                            ),
                         }),
                     });
                     world.add(sphere::MovingSphere {
                         center0: center,
-                        center1: center + Vec3::new(0., utility::random_double(0., 0.5), 0.),
+                        center1: center + Vec3::new(0., utility::random_double(rng, 0., 0.5), 0.),
                         time0: 0.,
                         time1: 1.,
                         r: 0.2,
@@ -91,8 +210,9 @@ pub fn random_scene() -> HittableList {
                 } else if choose_mat < 0.95 {
                     //metal
                     let sphere_material = Rc::new(Metal {
-                        albedo: Vec3::random(0.5, 1.),
-                        fuzz: utility::random_double(0., 0.5),
+                        albedo: Vec3::random(rng, 0.5, 1.),
+                        fuzz: utility::random_double(rng, 0., 0.5),
+                        film: None,
                     });
                     world.add(sphere::Sphere {
                         center,
@@ -101,7 +221,10 @@ pub fn random_scene() -> HittableList {
                     });
                 } else {
                     //glass
-                    let sphere_material = Rc::new(Dielectric { ref_idx: 1.5 });
+                    let sphere_material = Rc::new(Dielectric {
+                        ref_idx: 1.5,
+                        film: None,
+                    });
                     world.add(sphere::Sphere {
                         center,
                         r: 0.2,
@@ -111,7 +234,10 @@ pub fn random_scene() -> HittableList {
             }
         }
     }
-    let sphere_material = Rc::new(Dielectric { ref_idx: 1.5 });
+    let sphere_material = Rc::new(Dielectric {
+        ref_idx: 1.5,
+        film: None,
+    });
     world.add(sphere::Sphere {
         center: Vec3::new(0., 1., 0.),
         r: 1.,
@@ -132,6 +258,7 @@ pub fn random_scene() -> HittableList {
     let sphere_material = Rc::new(Metal {
         albedo: Vec3::new(0.7, 0.6, 0.5),
         fuzz: 0.,
+        film: None,
     });
     world.add(sphere::Sphere {
         center: Vec3::new(4., 1., 0.),
@@ -142,45 +269,519 @@ pub fn random_scene() -> HittableList {
     world
 }
 
+// 材质样例场景里第 slot 个球，每排 5 个，从后往前排
+fn showcase_sphere(slot: usize, mat_ptr: Rc<dyn Material>) -> sphere::Sphere {
+    sphere::Sphere {
+        center: Vec3::new(
+            -4. + 2. * (slot % 5) as f64,
+            0.8,
+            -2.5 + 2.5 * (slot / 5) as f64,
+        ),
+        r: 0.8,
+        mat_ptr,
+    }
+}
+
+// 浅色瓷砖和深色砖缝，作为没有图片文件时的贴图
+fn tile_texture() -> ImageTexture {
+    let (width, height) = (32, 32);
+    let mut data = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            data.push(if x % 16 == 0 || y % 16 == 0 {
+                Vec3::new(0.1, 0.1, 0.12)
+            } else if (x / 16 + y / 16) % 2 == 0 {
+                Vec3::new(0.85, 0.8, 0.7)
+            } else {
+                Vec3::new(0.3, 0.45, 0.7)
+            });
+        }
+    }
+    ImageTexture::from_data(width, height, data, WrapMode::Repeat)
+}
+
+// 排成网格的圆形凹坑，按切线空间法线贴图编码
+fn dimple_normals() -> ImageTexture {
+    let size = 64;
+    let mut data = Vec::with_capacity(size * size);
+    for y in 0..size {
+        for x in 0..size {
+            // 每 16 个纹素一个坑，坐标归一化到坑的半径
+            let dx = ((x % 16) as f64 + 0.5 - 8.) / 6.;
+            let dy = -((y % 16) as f64 + 0.5 - 8.) / 6.;
+            let r2 = dx * dx + dy * dy;
+            let normal = if r2 < 1. {
+                Vec3::new(-dx, -dy, (1. - r2).sqrt())
+            } else {
+                Vec3::new(0., 0., 1.)
+            };
+            data.push(normal * 0.5 + Vec3::ones() * 0.5);
+        }
+    }
+    ImageTexture::from_data(size, size, data, WrapMode::Repeat)
+}
+
+// 各种材质和纹理排成三排
+fn material_showcase() -> HittableList {
+    let mut world: HittableList = Default::default();
+    let checker = Rc::new(Checker {
+        odd: Rc::new(SolidColor::new(0.3, 0.3, 0.3)),
+        even: Rc::new(SolidColor::new(0.8, 0.8, 0.8)),
+        frequency: 1.,
+        mode: CheckerMode::World,
+    });
+    world.add(sphere::Sphere {
+        center: Vec3::new(0., -1000., 0.),
+        r: 1000.,
+        mat_ptr: Rc::new(Lambertian { albedo: checker }),
+    });
+
+    // 肥皂泡：两侧都是空气，颜色全部来自薄膜干涉
+    world.add(showcase_sphere(
+        0,
+        Rc::new(Dielectric {
+            ref_idx: 1.,
+            film: Some(ThinFilm::new(400., 1.33)),
+        }),
+    ));
+    // 表面有氧化膜的金属
+    world.add(showcase_sphere(
+        1,
+        Rc::new(Metal {
+            albedo: Vec3::new(0.8, 0.8, 0.85),
+            fuzz: 0.05,
+            film: Some(ThinFilm::new(250., 1.5)),
+        }),
+    ));
+    // 车漆：红色漫反射底层外面一层略带黄色的清漆
+    world.add(showcase_sphere(
+        2,
+        Rc::new(Coated {
+            base: Rc::new(Lambertian {
+                albedo: Rc::new(SolidColor::new(0.7, 0.05, 0.05)),
+            }),
+            ref_idx: 1.5,
+            roughness: 0.,
+            tint: Vec3::new(0.95, 0.9, 0.8),
+        }),
+    ));
+    // 陶土：粗糙漫反射，边缘比 Lambertian 更亮、中间更平
+    world.add(showcase_sphere(
+        3,
+        Rc::new(OrenNayar::new(
+            Rc::new(SolidColor::new(0.75, 0.45, 0.3)),
+            0.5,
+        )),
+    ));
+    // 蜡：光在内部随机游走，背光一侧也透出颜色
+    let wax = Subsurface::new(1.4, Rc::new(SolidColor::new(0.9, 0.8, 0.6)), 0.05);
+    world.add(SubsurfaceVolume::new(
+        Rc::new(showcase_sphere(4, Rc::new(wax.clone()))),
+        wax,
+    ));
+    // 雾：边界不可见，只有介质里的散射
+    let fog = Rc::new(SolidColor::new(0.9, 0.9, 0.9));
+    world.add(ConstantMedium::new(
+        Rc::new(showcase_sphere(
+            5,
+            Rc::new(Isotropic {
+                albedo: fog.clone(),
+            }),
+        )),
+        1.5,
+        fog,
+    ));
+    // 生锈的金属：金属和锈色漫反射按比例混合
+    world.add(showcase_sphere(
+        6,
+        Rc::new(Mix::new(
+            Rc::new(Metal {
+                albedo: Vec3::new(0.8, 0.6, 0.4),
+                fuzz: 0.2,
+                film: None,
+            }),
+            Rc::new(Lambertian {
+                albedo: Rc::new(SolidColor::new(0.45, 0.2, 0.08)),
+            }),
+            0.4,
+        )),
+    ));
+    // 镂空：按纹理坐标的棋盘格挖掉一半
+    world.add(showcase_sphere(
+        7,
+        Rc::new(Cutout {
+            material: Rc::new(Lambertian {
+                albedo: Rc::new(SolidColor::new(0.2, 0.6, 0.3)),
+            }),
+            alpha: Rc::new(Checker {
+                odd: Rc::new(SolidColor::new(0., 0., 0.)),
+                even: Rc::new(SolidColor::new(1., 1., 1.)),
+                frequency: 8.,
+                mode: CheckerMode::Uv,
+            }),
+        }),
+    ));
+    // 程序生成的图片贴图，带 MIP 和各向异性过滤
+    world.add(showcase_sphere(
+        8,
+        Rc::new(Lambertian {
+            albedo: Rc::new(TextureTransform {
+                uv_scale: (6., 3.),
+                uv_rotation: 30.,
+                ..TextureTransform::new(Rc::new(tile_texture()))
+            }),
+        }),
+    ));
+    // 凹凸贴图：噪声作为高度的粗糙石头
+    world.add(showcase_sphere(
+        9,
+        Rc::new(BumpMap {
+            material: Rc::new(Lambertian {
+                albedo: Rc::new(SolidColor::new(0.6, 0.6, 0.55)),
+            }),
+            height: Rc::new(NoiseTexture {
+                noise: Perlin::new(1),
+                scale: 6.,
+            }),
+            scale: 0.02,
+        }),
+    ));
+    // 法线贴图：高尔夫球一样的凹坑
+    world.add(showcase_sphere(
+        10,
+        Rc::new(NormalMap {
+            material: Rc::new(Lambertian {
+                albedo: Rc::new(SolidColor::new(0.9, 0.9, 0.9)),
+            }),
+            map: Rc::new(dimple_normals()),
+        }),
+    ));
+    // 大理石：灰度条纹经过色标上色
+    world.add(showcase_sphere(
+        11,
+        Rc::new(Lambertian {
+            albedo: Rc::new(ColorRamp::new(
+                Rc::new(Marble::new(4., 2)),
+                vec![
+                    (0., Vec3::new(0.25, 0.3, 0.3)),
+                    (0.6, Vec3::new(0.8, 0.8, 0.78)),
+                    (1., Vec3::new(0.95, 0.95, 0.93)),
+                ],
+            )),
+        }),
+    ));
+    // 木纹：年轮轴沿 y 方向
+    world.add(showcase_sphere(
+        12,
+        Rc::new(Lambertian {
+            albedo: Rc::new(Wood {
+                light: Rc::new(SolidColor::new(0.75, 0.5, 0.3)),
+                dark: Rc::new(SolidColor::new(0.4, 0.22, 0.1)),
+                noise: Perlin::new(3),
+                rings: 6.,
+                noise_scale: 2.,
+                turbulence: 0.3,
+            }),
+        }),
+    ));
+    // 细胞：Worley 噪声的细胞边界画成深色网格，细胞内叠一层 fBm 斑驳
+    let cell_edges = Remap {
+        input: Rc::new(Worley {
+            scale: 4.,
+            seed: 5,
+            mode: WorleyMode::F2MinusF1,
+        }),
+        from: (0., 0.15),
+        to: (0.1, 1.),
+        clamp: true,
+    };
+    world.add(showcase_sphere(
+        13,
+        Rc::new(Lambertian {
+            albedo: Rc::new(Multiply {
+                first: Rc::new(cell_edges),
+                second: Rc::new(ColorRamp::new(
+                    Rc::new(Fbm::new(4., 5)),
+                    vec![
+                        (0.3, Vec3::new(0.2, 0.5, 0.2)),
+                        (0.7, Vec3::new(0.7, 0.8, 0.3)),
+                    ],
+                )),
+            }),
+        }),
+    ));
+    // 雪山：脊状分形作为岩石，按高度渐变混入积雪
+    let snow_line = Remap {
+        input: Rc::new(Gradient {
+            kind: GradientKind::V,
+        }),
+        from: (0.7, 0.85),
+        to: (0., 1.),
+        clamp: true,
+    };
+    world.add(showcase_sphere(
+        14,
+        Rc::new(Lambertian {
+            albedo: Rc::new(MixTexture {
+                first: Rc::new(ColorRamp::new(
+                    Rc::new(RidgedMultifractal::new(3., 4)),
+                    vec![
+                        (0., Vec3::new(0.15, 0.12, 0.1)),
+                        (1., Vec3::new(0.55, 0.5, 0.45)),
+                    ],
+                )),
+                second: Rc::new(SolidColor::new(0.95, 0.95, 0.97)),
+                factor: Rc::new(snow_line),
+            }),
+        }),
+    ));
+
+    world
+}
+
+fn material_showcase_camera() -> Camera {
+    Camera::new(
+        Vec3::new(0., 5., 13.),
+        Vec3::new(0., 0.5, 0.),
+        Vec3::new(0., 1., 0.),
+        32.,
+        ASPECT_RATIO,
+        0.,
+        13.,
+        0.,
+        1.,
+    )
+}
+
 fn main() {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            exit(2);
+        }
+    };
+    // 处理函数整个进程只能装一次，动画的每一帧都检查同一个标志
+    if matches!(options.command, Command::Render | Command::Animate { .. }) {
+        install_interrupt_handler();
+    }
+    let result = match &options.command {
+        Command::Render => run(&options),
+        Command::Tonemap { input } => tonemap(input, &options, false),
+        Command::Denoise { input } => tonemap(input, &options, true),
+        Command::Animate { first, last } => animate(&options, *first, *last),
+    };
+    if let Err(message) = result {
+        eprintln!("{} {}", style("error:").red(), message);
+        exit(1);
+    }
+}
+
+// 对保存的 EXR 重新做显示变换（可以先降噪），不需要重新渲染
+fn tonemap(input: &str, options: &Options, denoise: bool) -> Result<(), String> {
+    let path = options.output.as_str();
+    let format = OutputFormat::from_path(path, options.quality).map_err(|e| e.to_string())?;
+    let image = exr::read_exr(input).map_err(|e| format!("failed to read \"{}\": {}", input, e))?;
+    let mut fb = image
+        .framebuffer("")
+        .map_err(|e| format!("failed to read \"{}\": {}", input, e))?;
+    if denoise {
+        let guides = Guides::from_exr(&image);
+        fb = denoise::denoise(&fb, &guides, &DenoiseSettings::default());
+    }
+    output::save(&fb, path, format, &options.display)
+        .map_err(|e| format!("failed to write \"{}\": {}", path, e))?;
+    println!("Output image as \"{}\"", style(path).yellow());
+    Ok(())
+}
+
+// 一张图用到的文件，动画的每一帧各不相同
+struct ImageFiles {
+    output: String,
+    checkpoint: String,
+    overlay: Option<String>,
+    heatmap: Option<String>,
+    resume: bool,
+}
+
+fn clear_screen() {
     print!("{}[2J", 27 as char); // Clear screen
     print!("{esc}[2J{esc}[1;1H", esc = 27 as char); // Set cursor position as 1,1
-                                                    // Image
-    let aspect_ratio = 16.0 / 9.0;
-    let width = 400;
-    let height = (width as f64 / aspect_ratio) as u32;
-    let quality = 60; // From 0 to 100
-    let path = "output/output.jpg";
+}
 
+fn run(options: &Options) -> Result<(), String> {
+    clear_screen();
     let lookfrom = Vec3::new(13., 2., 3.);
     let lookat = Vec3::new(0., 0., 0.);
     let vup = Vec3::new(0., 1., 0.);
     let focus_dist = 10.0; //(lookfrom - lookat).length();
     let aperture = 0.1;
-    let cam: Camera = Camera::new(
-        lookfrom,
-        lookat,
-        vup,
-        20.,
-        aspect_ratio,
-        aperture,
-        focus_dist,
-        0.,
-        1.,
-    );
-    let samples_per_pixel = 100;
-    let max_depth = 50;
+    let cam: Camera = match options.scene {
+        Scene::Materials => material_showcase_camera(),
+        _ => Camera::new(
+            lookfrom,
+            lookat,
+            vup,
+            20.,
+            ASPECT_RATIO,
+            aperture,
+            focus_dist,
+            0.,
+            1.,
+        ),
+    };
+    // 随机场景由种子决定，同一个种子总是得到同一个场景
+    let world: HittableList = match options.scene {
+        Scene::TwoSpheres => two_spheres(),
+        Scene::Random => random_scene(&mut RandGen::seed_from_u64(options.seed)),
+        Scene::Materials => material_showcase(),
+    };
+    let files = ImageFiles {
+        output: options.output.clone(),
+        checkpoint: options.checkpoint.clone(),
+        overlay: options.overlay.clone(),
+        heatmap: Some(HEATMAP_PATH.to_string()),
+        resume: options.resume,
+    };
+    render_image(options, &world, cam, &files)?;
+    Ok(())
+}
 
-    let world: HittableList = two_spheres();
+// 依次渲染 first 到 last 帧，输出、检查点、叠加的底图和样本数热力图都按帧编号；--resume 只用于第一帧
+fn animate(options: &Options, first: u32, last: u32) -> Result<(), String> {
+    clear_screen();
+    let timeline = Timeline {
+        fps: options.fps,
+        shutter: options.shutter,
+    };
+    let camera = two_spheres_camera();
+    for frame in first..=last {
+        let (time0, time1) = timeline.shutter_interval(frame);
+        println!(
+            "Frame {} of {}..{} (t = {:.3}s)",
+            style(frame).yellow(),
+            first,
+            last,
+            time0
+        );
+        let world = animated_two_spheres(time0, time1);
+        let cam = camera.camera(time0, time1, ASPECT_RATIO);
+        let files = ImageFiles {
+            output: output::numbered_path(&options.output, frame),
+            checkpoint: output::numbered_path(&options.checkpoint, frame),
+            overlay: options
+                .overlay
+                .as_ref()
+                .map(|base| output::numbered_path(base, frame)),
+            heatmap: Some(output::numbered_path(HEATMAP_PATH, frame)),
+            resume: options.resume && frame == first,
+        };
+        if render_image(options, &world, cam, &files)? == StopReason::Interrupted {
+            return Err(format!("interrupted at frame {}", frame));
+        }
+    }
+    Ok(())
+}
+
+// 渲染一张图并写出所有结果，返回渲染停下的原因
+fn render_image(
+    options: &Options,
+    world: &HittableList,
+    cam: Camera,
+    files: &ImageFiles,
+) -> Result<StopReason, String> {
+    let width = IMAGE_WIDTH;
+    let height = (width as f64 / ASPECT_RATIO) as u32;
+    let quality = options.quality; // From 0 to 100
+                                   // 裁剪区域内的光线与渲染整张图时完全相同
+    let crop = match options.crop {
+        Some(bounds) if options.crop_normalized => {
+            CropWindow::from_normalized(bounds, width, height)
+        }
+        Some(bounds) => CropWindow::from_pixels(bounds, width, height),
+        None => Some(CropWindow::full(width, height)),
+    }
+    .ok_or_else(|| "the crop window is empty".to_string())?;
+    let mut settings = RenderSettings {
+        width,
+        height,
+        samples_per_pixel: 100,
+        max_depth: 50,
+        seed: options.seed, // 相同的种子渲染出逐位相同的图片
+        sampler: SamplerKind::Sobol,
+        error_threshold: 0.05, // 设为 0 关闭自适应采样
+        min_samples: 16,
+        batch_samples: 8,
+        time_limit: None, // 例如 Some(Duration::from_secs(60))
+        target_noise: 0.,
+        filter: options.filter.unwrap_or_default(),
+        crop,
+    };
+    let path = files.output.as_str();
+    let checkpoint_path = files.checkpoint.as_str();
+    let heatmap_path = files.heatmap.as_deref();
+    // 在渲染之前检查输出格式，不要渲染完才发现写不出去
+    let format = OutputFormat::from_path(path, quality).map_err(|e| e.to_string())?;
+    let heatmap_format = match heatmap_path {
+        Some(heatmap_path) => {
+            Some(OutputFormat::from_path(heatmap_path, quality).map_err(|e| e.to_string())?)
+        }
+        None => None,
+    };
+
+    // 恢复时设置以检查点里的为准，场景和相机要与中断前相同
+    let mut renderer = if files.resume {
+        checkpoint::load(checkpoint_path, cam)
+            .map_err(|e| format!("failed to load checkpoint \"{}\": {}", checkpoint_path, e))?
+    } else {
+        Renderer::new(settings, cam)
+    };
+    // 已有的样本是按检查点里的滤波器分摊的，中途不能更换
+    if let Some(filter) = options.filter {
+        if filter != renderer.settings.filter {
+            return Err("the checkpoint was rendered with a different pixel filter".to_string());
+        }
+    }
+    if options.crop.is_some() && crop != renderer.settings.crop {
+        return Err("the checkpoint was rendered with a different crop window".to_string());
+    }
+    if let Some(time_limit) = options.time_limit {
+        renderer.settings.time_limit = Some(time_limit);
+    }
+    if let Some(target_noise) = options.target_noise {
+        renderer.settings.target_noise = target_noise;
+    }
+    settings = renderer.settings;
+    let (width, height) = (settings.width, settings.height);
+    let crop = settings.crop;
 
     println!(
-        "Image size: {}\nJPEG quality: {}",
+        "Image size: {}\nOutput format: {}",
         style(width.to_string() + &"x".to_string() + &height.to_string()).yellow(),
-        style(quality.to_string()).yellow(),
+        style(format!("{:?}", format)).yellow(),
     );
+    if crop != CropWindow::full(width, height) {
+        println!(
+            "Crop window: {}",
+            style(format!(
+                "({}, {}) - ({}, {})",
+                crop.x0, crop.y0, crop.x1, crop.y1
+            ))
+            .yellow()
+        );
+    }
+
+    // 有 --overlay 时贴到之前的整张图上，否则只写裁剪区域；extra 是 EXR 里的其它图层
+    let write_image = |fb: &Framebuffer, extra: Vec<ExrChannel>| match &files.overlay {
+        Some(base) => output::save_overlay(fb, extra, crop, base, path, format, &options.display),
+        None if extra.is_empty() => output::save(fb, path, format, &options.display),
+        None => {
+            let mut channels = exr::framebuffer_channels(fb);
+            channels.extend(extra);
+            output::save_exr(path, fb.width, fb.height, &channels)
+        }
+    };
 
-    // Create image data
-    let mut img: RgbImage = ImageBuffer::new(width, height);
     // Progress bar UI powered by library `indicatif`
     // Get environment variable CI, which is true for GitHub Action
     let progress = if option_env!("CI").unwrap_or_default() == "true" {
@@ -193,34 +794,107 @@ fn main() {
         .progress_chars("#>-"));
 
     // Generate image
-    for y in (0..height).rev() {
-        for x in 0..width {
-            let mut color = Vec3::new(0., 0., 0.);
-            let mut i = 0;
-            while i < samples_per_pixel {
-                let u = (x as f64 + utility::random_double(0., 1.)) / width as f64;
-                let v = (y as f64 + utility::random_double(0., 1.)) / height as f64;
-                let r = Camera::get_ray(cam, u, v);
-                color += Ray::ray_color(r, &world, max_depth);
-                i += 1;
-            }
-            let pixel_color = utility::get_pixel_color(color, samples_per_pixel);
-            let pixel = img.get_pixel_mut(x, height - y - 1);
-            *pixel = image::Rgb(pixel_color);
-            progress.inc(1);
-        }
+    // 每一遍结束都写出当前结果和检查点；超时、达到目标噪声或按 Ctrl-C 时停下并写出最终图片
+    if files.resume {
+        println!(
+            "Resume from \"{}\" with {} samples",
+            style(checkpoint_path).yellow(),
+            renderer.total_samples()
+        );
     }
+    let reason = renderer.render(world, &progress, |renderer, _| {
+        if let Err(err) = write_image(&renderer.framebuffer(), Vec::new()) {
+            println!(
+                "{}",
+                style(format!("Writing intermediate image fails: {}", err)).red()
+            );
+        }
+        if checkpoint::save(renderer, checkpoint_path).is_err() {
+            println!("{}", style("Writing checkpoint fails.").red());
+        }
+    });
     progress.finish();
+    if checkpoint::save(&renderer, checkpoint_path).is_err() {
+        println!("{}", style("Writing checkpoint fails.").red());
+    }
+    println!(
+        "Stopped: {:?}\nAverage samples per pixel: {:.1}\nNoise level: {:.4}",
+        reason,
+        renderer.total_samples() as f64 / (crop.width() * crop.height()) as f64,
+        renderer.noise_level(),
+    );
 
     // Output image to file
-    println!("Ouput image as \"{}\"", style(path).yellow());
-    let output_image = image::DynamicImage::ImageRgb8(img);
-    let mut output_file = File::create(path).unwrap();
-    match output_image.write_to(&mut output_file, image::ImageOutputFormat::Jpeg(quality)) {
-        Ok(_) => {}
-        // Err(_) => panic!("Outputting image fails."),
-        Err(_) => println!("{}", style("Outputting image fails.").red()),
+    // 输出为 EXR 时 AOV 作为图层写进同一个文件，否则每个 AOV 单独写一个 EXR
+    let mut fb = renderer.framebuffer();
+    let aovs = if options.aovs.is_empty() && !options.denoise {
+        None
+    } else {
+        println!("Rendering AOVs");
+        Some(aov::render_aovs(
+            world,
+            cam,
+            width,
+            height,
+            crop,
+            settings.seed,
+        ))
+    };
+    let variance = renderer.variance_buffer();
+    if let (Some(aovs), true) = (&aovs, options.denoise) {
+        println!("Denoising");
+        let guides = Guides::from_aovs(aovs, variance.clone());
+        fb = denoise::denoise(&fb, &guides, &DenoiseSettings::default());
+    }
+    println!("Output image as \"{}\"", style(path).yellow());
+    match &aovs {
+        Some(aovs) if format == OutputFormat::Exr => {
+            // 同时写出方差，之后可以用 raytracer denoise 单独降噪
+            let mut channels = Vec::new();
+            for kind in &options.aovs {
+                channels.extend(aovs.channels(*kind));
+            }
+            let variance = variance.iter().map(|v| *v as f32).collect();
+            channels.push(ExrChannel::new("variance.Y", variance));
+            write_image(&fb, channels)
+        }
+        _ => write_image(&fb, Vec::new()),
+    }
+    .map_err(|e| format!("failed to write \"{}\": {}", path, e))?;
+    if let (Some(aovs), false) = (&aovs, format == OutputFormat::Exr) {
+        for kind in &options.aovs {
+            let aov_path = output::sibling_path(path, kind.name(), "exr");
+            println!(
+                "Output {} as \"{}\"",
+                kind.name(),
+                style(&aov_path).yellow()
+            );
+            // 叠加时贴到底图旁边同名的 AOV 文件上，没有这个文件就只写裁剪区域
+            let channels = aovs.channels(*kind);
+            let base_aov = files
+                .overlay
+                .as_ref()
+                .map(|base| output::sibling_path(base, kind.name(), "exr"))
+                .filter(|base_aov| Path::new(base_aov).exists());
+            match base_aov {
+                Some(base_aov) => output::save_exr_overlay(&base_aov, &aov_path, &channels, crop),
+                None => output::save_exr(&aov_path, crop.width(), crop.height(), &channels),
+            }
+            .map_err(|e| format!("failed to write \"{}\": {}", aov_path, e))?;
+        }
+    }
+    if let (Some(heatmap_path), Some(heatmap_format)) = (heatmap_path, heatmap_format) {
+        println!(
+            "Output sample count heatmap as \"{}\"",
+            style(heatmap_path).yellow()
+        );
+        output::save_ldr(
+            &renderer.sample_count_heatmap(),
+            heatmap_path,
+            heatmap_format,
+        )
+        .map_err(|e| format!("failed to write \"{}\": {}", heatmap_path, e))?;
     }
 
-    exit(0);
+    Ok(reason)
 }
@@ -0,0 +1,140 @@
+use std::rc::Rc;
+
+use super::{scatter_with_shading_normal, Material, ScatterRecord};
+use crate::{
+    basic::{ray::Ray, vec::Vec3},
+    hittable::HitRecord,
+    sampler::Sampler,
+    texture::Texture,
+};
+
+// 凹凸贴图，高度取任意纹理的第一个通道，乘以 scale 后沿法向偏移表面
+#[derive(Clone)]
+pub struct BumpMap {
+    pub material: Rc<dyn Material>,
+    pub height: Rc<dyn Texture>,
+    pub scale: f64,
+}
+
+impl BumpMap {
+    fn displacement(&self, rec: &HitRecord, u: f64, v: f64, p: Vec3) -> f64 {
+        self.height.get_filtered_value(u, v, p, &rec.footprint).x * self.scale
+    }
+
+    // 用有限差分求位移后的 dp/du、dp/dv，叉乘得到新法向量
+    pub fn perturb(&self, rec: &mut HitRecord) {
+        if rec.dpdu.squared_length() == 0. || rec.dpdv.squared_length() == 0. {
+            return;
+        }
+        let outward = if rec.front_face {
+            rec.normal
+        } else {
+            -rec.normal
+        };
+        let fp = rec.footprint;
+        let mut du = 0.5 * (fp.dudx.abs() + fp.dudy.abs());
+        if du == 0. {
+            du = 0.0005;
+        }
+        let mut dv = 0.5 * (fp.dvdx.abs() + fp.dvdy.abs());
+        if dv == 0. {
+            dv = 0.0005;
+        }
+
+        let d = self.displacement(rec, rec.u, rec.v, rec.local_p);
+        let d_u = self.displacement(rec, rec.u + du, rec.v, rec.local_p + rec.dpdu * du);
+        let d_v = self.displacement(rec, rec.u, rec.v + dv, rec.local_p + rec.dpdv * dv);
+        let dpdu = rec.dpdu + outward * ((d_u - d) / du);
+        let dpdv = rec.dpdv + outward * ((d_v - d) / dv);
+
+        let perturbed = Vec3::cross(dpdu, dpdv);
+        if perturbed.squared_length() == 0. {
+            return;
+        }
+        let mut perturbed = Vec3::unit(perturbed);
+        if perturbed * outward < 0. {
+            perturbed = -perturbed;
+        }
+        rec.normal = if rec.front_face {
+            perturbed
+        } else {
+            -perturbed
+        };
+        rec.dpdu = dpdu;
+        rec.dpdv = dpdv;
+    }
+}
+
+impl Material for BumpMap {
+    fn scatter(
+        &self,
+        r_in: Ray,
+        mut rec: HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        self.perturb(&mut rec);
+        scatter_with_shading_normal(self.material.as_ref(), r_in, rec, sampler)
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Vec3 {
+        self.material.albedo(rec)
+    }
+
+    fn shading_normal(&self, rec: &HitRecord) -> Vec3 {
+        let mut rec = rec.clone();
+        self.perturb(&mut rec);
+        self.material.shading_normal(&rec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        material::{lambertian::Lambertian, tests::hit_unit_sphere},
+        texture::solid_color::SolidColor,
+    };
+
+    // 高度等于纹理坐标 u
+    struct RampU;
+
+    impl Texture for RampU {
+        fn get_color_value(&self, u: f64, _v: f64, _p: Vec3) -> Vec3 {
+            Vec3::ones() * u
+        }
+    }
+
+    fn bumped(height: Rc<dyn Texture>) -> (Rc<BumpMap>, HitRecord) {
+        let base = Rc::new(Lambertian {
+            albedo: Rc::new(SolidColor::new(0.5, 0.5, 0.5)),
+        });
+        let (_, rec) = hit_unit_sphere(
+            base.clone(),
+            Vec3::new(0.3, 0.2, 3.),
+            Vec3::new(0., 0., -1.),
+        );
+        let bump = Rc::new(BumpMap {
+            material: base,
+            height,
+            scale: 0.1,
+        });
+        (bump, rec)
+    }
+
+    // 高度处处相同时表面没有起伏，法向量不变
+    #[test]
+    fn constant_height_keeps_the_normal() {
+        let (bump, rec) = bumped(Rc::new(SolidColor::new(0.7, 0.7, 0.7)));
+        assert!((bump.shading_normal(&rec) - rec.normal).length() < 1e-12);
+    }
+
+    // 高度沿 u 增加时法向量向 -dp/du 方向倾斜
+    #[test]
+    fn slope_tilts_the_normal_downhill() {
+        let (bump, rec) = bumped(Rc::new(RampU));
+        let normal = bump.shading_normal(&rec);
+        assert!((normal.length() - 1.).abs() < 1e-12);
+        assert!(normal * rec.dpdu < -1e-3);
+        assert!(normal * rec.normal > 0.);
+    }
+}
@@ -0,0 +1,160 @@
+use std::rc::Rc;
+
+use super::{dielectric::Dielectric, Material, ScatterRecord};
+use crate::{
+    basic::{ray::Ray, vec::Vec3},
+    hittable::HitRecord,
+    sampler::Sampler,
+    utility,
+};
+
+// 在任意材质外面套一层透明涂层（清漆、车漆），涂层本身相当于一层很薄的 Dielectric
+#[derive(Clone)]
+pub struct Coated {
+    pub base: Rc<dyn Material>,
+    pub ref_idx: f64,   // 涂层折射率
+    pub roughness: f64, // 涂层表面粗糙度，0 为光滑
+    pub tint: Vec3,     // 垂直穿过涂层再返回后剩下的颜色，描述涂层吸收
+}
+
+impl Coated {
+    // 在涂层内的折射角余弦
+    fn cos_inside(&self, cos: f64) -> f64 {
+        let sin2 = (1. - cos * cos) / (self.ref_idx * self.ref_idx);
+        (1. - sin2).max(0.).sqrt()
+    }
+
+    // 光线斜穿涂层时路程更长，吸收按 Beer 定律随路程指数增长
+    fn absorption(&self, cos_in: f64, cos_out: f64) -> Vec3 {
+        let distance = 0.5 * (1. / self.cos_inside(cos_in) + 1. / self.cos_inside(cos_out));
+        Vec3::new(
+            self.tint.x.powf(distance),
+            self.tint.y.powf(distance),
+            self.tint.z.powf(distance),
+        )
+    }
+}
+
+impl Material for Coated {
+    fn scatter(
+        &self,
+        r_in: Ray,
+        rec: HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        if !rec.front_face {
+            return self.base.scatter(r_in, rec, sampler);
+        }
+        let unit_direction = Vec3::unit(r_in.dir);
+        let cos_in = utility::fmin(-unit_direction * rec.normal, 1.);
+        // 按涂层的 Fresnel 反射率选择在涂层表面反射还是进入底层材质
+        if Dielectric::reflectance(cos_in, self.ref_idx) > sampler.get_1d() {
+            let reflected = Vec3::reflect(unit_direction, rec.normal);
+            let scattered = Ray::new(
+                rec.p,
+                reflected + Vec3::random_in_unit_sphere(sampler.rng()) * self.roughness,
+                r_in.time,
+            );
+            if scattered.dir * rec.normal > 0. {
+                return Some(ScatterRecord {
+                    scattered,
+                    attenuation: Vec3::ones(),
+                });
+            }
+            return None;
+        }
+        let normal = rec.normal;
+        let mut base_scatter = self.base.scatter(r_in, rec, sampler)?;
+        let cos_out = utility::fmin(Vec3::unit(base_scatter.scattered.dir) * normal, 1.);
+        if cos_out > 0. {
+            base_scatter.attenuation =
+                Vec3::elemul(base_scatter.attenuation, self.absorption(cos_in, cos_out));
+        }
+        Some(base_scatter)
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Vec3 {
+        self.base.albedo(rec).elemul(self.tint)
+    }
+
+    fn shading_normal(&self, rec: &HitRecord) -> Vec3 {
+        self.base.shading_normal(rec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        material::{lambertian::Lambertian, tests::hit_unit_sphere},
+        sampler::independent::IndependentSampler,
+        texture::solid_color::SolidColor,
+    };
+
+    fn coated(tint: Vec3) -> Rc<Coated> {
+        Rc::new(Coated {
+            base: Rc::new(Lambertian {
+                albedo: Rc::new(SolidColor::new(0.5, 0.5, 0.5)),
+            }),
+            ref_idx: 1.5,
+            roughness: 0.,
+            tint,
+        })
+    }
+
+    // 在涂层上反射的比例等于涂层的 Fresnel 反射率，斜射时明显更多
+    #[test]
+    fn coat_reflects_with_fresnel_probability() {
+        let material = coated(Vec3::ones());
+        let mut sampler = IndependentSampler::new(7);
+        for &x in &[0., 0.9] {
+            let (ray, rec) = hit_unit_sphere(
+                material.clone(),
+                Vec3::new(x, 0., 3.),
+                Vec3::new(0., 0., -1.),
+            );
+            let cos = (1. - x * x).sqrt();
+            let n = 20000;
+            let mut reflected = 0;
+            for i in 0..n {
+                sampler.start_sample(0, i);
+                let scatter = material.scatter(ray, rec.clone(), &mut sampler).unwrap();
+                if scatter.attenuation == Vec3::ones() {
+                    reflected += 1;
+                    let mirror = Vec3::reflect(Vec3::unit(ray.dir), rec.normal);
+                    assert!((Vec3::unit(scatter.scattered.dir) - mirror).length() < 1e-12);
+                }
+            }
+            let expected = Dielectric::reflectance(cos, 1.5);
+            assert!(
+                (reflected as f64 / n as f64 - expected).abs() < 0.01,
+                "{}",
+                x
+            );
+        }
+    }
+
+    // 进入底层的光线被涂层吸收，颜色为底层颜色乘上涂层颜色，斜出时吸收更多
+    #[test]
+    fn coat_absorbs_along_the_path() {
+        let material = coated(Vec3::new(0.5, 1., 1.));
+        let (ray, rec) = hit_unit_sphere(
+            material.clone(),
+            Vec3::new(0., 0., 3.),
+            Vec3::new(0., 0., -1.),
+        );
+        let mut sampler = IndependentSampler::new(3);
+        for i in 0..1000 {
+            sampler.start_sample(0, i);
+            let scatter = material.scatter(ray, rec.clone(), &mut sampler).unwrap();
+            if scatter.attenuation == Vec3::ones() {
+                continue;
+            }
+            let cos_out = Vec3::unit(scatter.scattered.dir) * rec.normal;
+            let expected = 0.5 * material.absorption(1., cos_out).x;
+            assert!((scatter.attenuation.x - expected).abs() < 1e-12);
+            assert!(scatter.attenuation.x <= 0.25 + 1e-12);
+            assert!((scatter.attenuation.y - 0.5).abs() < 1e-12);
+        }
+    }
+}
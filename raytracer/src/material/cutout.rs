@@ -0,0 +1,103 @@
+use std::rc::Rc;
+
+use super::{Material, ScatterRecord};
+use crate::{
+    basic::{ray::Ray, vec::Vec3},
+    hittable::HitRecord,
+    sampler::Sampler,
+    texture::Texture,
+};
+
+// 透明度遮罩（树叶、栅栏），alpha 取纹理的第一个通道
+// 透明处光线不折射、不衰减，直接沿原方向穿过
+#[derive(Clone)]
+pub struct Cutout {
+    pub material: Rc<dyn Material>,
+    pub alpha: Rc<dyn Texture>,
+}
+
+impl Material for Cutout {
+    fn scatter(
+        &self,
+        r_in: Ray,
+        rec: HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        let alpha = self
+            .alpha
+            .get_filtered_value(rec.u, rec.v, rec.local_p, &rec.footprint)
+            .x;
+        if alpha > sampler.get_1d() {
+            self.material.scatter(r_in, rec, sampler)
+        } else {
+            Some(ScatterRecord {
+                scattered: Ray::new(rec.p, r_in.dir, r_in.time),
+                attenuation: Vec3::ones(),
+            })
+        }
+    }
+
+    // 透明的部分光线原样穿过，按 alpha 与白色混合
+    fn albedo(&self, rec: &HitRecord) -> Vec3 {
+        let alpha = self
+            .alpha
+            .get_filtered_value(rec.u, rec.v, rec.local_p, &rec.footprint)
+            .x;
+        self.material.albedo(rec) * alpha + Vec3::ones() * (1. - alpha)
+    }
+
+    fn shading_normal(&self, rec: &HitRecord) -> Vec3 {
+        self.material.shading_normal(rec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        material::{lambertian::Lambertian, tests::hit_unit_sphere},
+        sampler::independent::IndependentSampler,
+        texture::solid_color::SolidColor,
+    };
+
+    fn cutout(alpha: f64) -> (Rc<Lambertian>, Rc<Cutout>) {
+        let material = Rc::new(Lambertian {
+            albedo: Rc::new(SolidColor::new(0.2, 0.4, 0.6)),
+        });
+        let cutout = Rc::new(Cutout {
+            material: material.clone(),
+            alpha: Rc::new(SolidColor::new(alpha, alpha, alpha)),
+        });
+        (material, cutout)
+    }
+
+    // alpha 为 0 时光线原样穿过，为 1 时与里面的材质完全相同
+    #[test]
+    fn alpha_selects_pass_through_or_material() {
+        let (_, clear) = cutout(0.);
+        let (material, opaque) = cutout(1.);
+        let (ray, rec) = hit_unit_sphere(
+            clear.clone(),
+            Vec3::new(0.2, 0., 3.),
+            Vec3::new(0., 0., -1.),
+        );
+        let mut a = IndependentSampler::new(9);
+        let mut b = IndependentSampler::new(9);
+        for i in 0..100 {
+            a.start_sample(0, i);
+            let passed = clear.scatter(ray, rec.clone(), &mut a).unwrap();
+            assert!(passed.scattered.dir == ray.dir && passed.scattered.orig == rec.p);
+            assert!(passed.attenuation == Vec3::ones());
+
+            a.start_sample(0, i);
+            b.start_sample(0, i);
+            // Cutout 先用掉一维判断 alpha
+            a.get_1d();
+            let expected = material.scatter(ray, rec.clone(), &mut a).unwrap();
+            let scatter = opaque.scatter(ray, rec.clone(), &mut b);
+            assert!(scatter.unwrap().scattered.dir == expected.scattered.dir);
+        }
+        assert!(clear.albedo(&rec) == Vec3::ones());
+        assert!(opaque.albedo(&rec) == material.albedo(&rec));
+    }
+}
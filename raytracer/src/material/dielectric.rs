@@ -1,13 +1,15 @@
-use super::{Material, ScatterRecord};
+use super::{thin_film::ThinFilm, Material, ScatterRecord};
 use crate::{
     basic::{ray::Ray, vec::Vec3},
     hittable::HitRecord,
+    sampler::Sampler,
     utility,
 };
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct Dielectric {
     pub ref_idx: f64,
+    pub film: Option<ThinFilm>, // 表面镀膜，None 为普通玻璃
 }
 
 impl Dielectric {
@@ -20,28 +22,44 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, r_in: Ray, rec: HitRecord) -> Option<ScatterRecord> {
-        let refraction_ratio;
-        if rec.front_face {
-            refraction_ratio = 1. / self.ref_idx;
+    fn scatter(
+        &self,
+        r_in: Ray,
+        rec: HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        let (eta_i, eta_t) = if rec.front_face {
+            (1., self.ref_idx)
         } else {
-            refraction_ratio = self.ref_idx;
-        }
+            (self.ref_idx, 1.)
+        };
+        let refraction_ratio = eta_i / eta_t;
         let unit_direction = Vec3::unit(r_in.dir);
         let cos = utility::fmin(-unit_direction * rec.normal, 1.);
         let sin = (1. - cos * cos).sqrt();
         let cannot_refract = refraction_ratio * sin > 1.;
-        let direction;
-        if cannot_refract
-            || Dielectric::reflectance(cos, refraction_ratio) > utility::random_double(0., 1.)
-        {
-            direction = Vec3::reflect(unit_direction, rec.normal);
+        let reflectance = match &self.film {
+            Some(film) => film.reflectance(cos, eta_i, Vec3::ones() * eta_t, &rec),
+            None => Vec3::ones() * Dielectric::reflectance(cos, refraction_ratio),
+        };
+        // 各通道反射率不同时，按平均反射率选择反射或折射，再用衰减修正各通道
+        let reflect_prob = (reflectance.x + reflectance.y + reflectance.z) / 3.;
+        let (direction, attenuation) = if cannot_refract {
+            (Vec3::reflect(unit_direction, rec.normal), Vec3::ones())
+        } else if reflect_prob > sampler.get_1d() {
+            (
+                Vec3::reflect(unit_direction, rec.normal),
+                reflectance / reflect_prob,
+            )
         } else {
-            direction = Vec3::refract(unit_direction, rec.normal, refraction_ratio);
-        }
+            (
+                Vec3::refract(unit_direction, rec.normal, refraction_ratio),
+                (Vec3::ones() - reflectance) / (1. - reflect_prob),
+            )
+        };
         Some(ScatterRecord {
             scattered: Ray::new(rec.p, direction, r_in.time),
-            attenuation: Vec3::new(1., 1., 1.),
+            attenuation,
         })
     }
 }
@@ -0,0 +1,40 @@
+use std::rc::Rc;
+
+use super::{Material, ScatterRecord};
+use crate::{
+    basic::{ray::Ray, vec::Vec3},
+    hittable::HitRecord,
+    sampler::Sampler,
+    texture::Texture,
+};
+
+// 参与介质的相函数，向各个方向均匀散射
+#[derive(Clone)]
+pub struct Isotropic {
+    pub albedo: Rc<dyn Texture>,
+}
+
+impl Material for Isotropic {
+    fn scatter(
+        &self,
+        r_in: Ray,
+        rec: HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        Some(ScatterRecord {
+            scattered: Ray::new(
+                rec.p,
+                Vec3::unit_sphere_from_square(sampler.get_2d()),
+                r_in.time,
+            ),
+            attenuation: self
+                .albedo
+                .get_filtered_value(rec.u, rec.v, rec.local_p, &rec.footprint),
+        })
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Vec3 {
+        self.albedo
+            .get_filtered_value(rec.u, rec.v, rec.local_p, &rec.footprint)
+    }
+}
@@ -4,6 +4,7 @@ use super::{Material, ScatterRecord};
 use crate::{
     basic::{ray::Ray, vec::Vec3},
     hittable::HitRecord,
+    sampler::Sampler,
     texture::Texture,
 };
 
@@ -13,11 +14,23 @@ pub struct Lambertian {
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, r_in: Ray, rec: HitRecord) -> Option<ScatterRecord> {
-        let scatter_direction = rec.normal + Vec3::random_unit_sphere();
+    fn scatter(
+        &self,
+        r_in: Ray,
+        rec: HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        let scatter_direction = rec.normal + Vec3::unit_sphere_from_square(sampler.get_2d());
         Some(ScatterRecord {
             scattered: Ray::new(rec.p, scatter_direction, r_in.time),
-            attenuation: self.albedo.get_color_value(rec.u, rec.v, rec.p),
+            attenuation: self
+                .albedo
+                .get_filtered_value(rec.u, rec.v, rec.local_p, &rec.footprint),
         })
     }
+
+    fn albedo(&self, rec: &HitRecord) -> Vec3 {
+        self.albedo
+            .get_filtered_value(rec.u, rec.v, rec.local_p, &rec.footprint)
+    }
 }
@@ -1,29 +1,48 @@
-use super::{Material, ScatterRecord};
+use super::{thin_film::ThinFilm, Material, ScatterRecord};
 use crate::{
     basic::{ray::Ray, vec::Vec3},
     hittable::HitRecord,
+    sampler::Sampler,
 };
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct Metal {
     pub albedo: Vec3,
     pub fuzz: f64,
+    pub film: Option<ThinFilm>, // 表面氧化层等薄膜，None 为裸金属
 }
 impl Material for Metal {
-    fn scatter(&self, r_in: Ray, rec: HitRecord) -> Option<ScatterRecord> {
-        let reflected = Vec3::reflect(Vec3::unit(r_in.dir), rec.normal);
+    fn scatter(
+        &self,
+        r_in: Ray,
+        rec: HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        let unit_direction = Vec3::unit(r_in.dir);
+        let reflected = Vec3::reflect(unit_direction, rec.normal);
         let scattered = Ray::new(
             rec.p,
-            reflected + Vec3::random_in_unit_sphere() * self.fuzz,
+            reflected + Vec3::random_in_unit_sphere(sampler.rng()) * self.fuzz,
             r_in.time,
         );
         if scattered.dir * rec.normal > 0. {
+            let attenuation = match &self.film {
+                Some(film) => {
+                    let cos = (-unit_direction * rec.normal).min(1.);
+                    film.reflectance(cos, 1., ThinFilm::ior_from_f0(self.albedo), &rec)
+                }
+                None => self.albedo,
+            };
             Some(ScatterRecord {
                 scattered,
-                attenuation: self.albedo,
+                attenuation,
             })
         } else {
             None
         }
     }
+
+    fn albedo(&self, _rec: &HitRecord) -> Vec3 {
+        self.albedo
+    }
 }
@@ -0,0 +1,127 @@
+use std::rc::Rc;
+
+use super::{Material, ScatterRecord};
+use crate::{
+    basic::{ray::Ray, vec::Vec3},
+    hittable::HitRecord,
+    sampler::Sampler,
+    texture::{solid_color::SolidColor, Texture},
+};
+
+// 按权重随机选择两种材质之一（金属上的锈、石头上的苔藓、贴花）
+// 权重取纹理的第一个通道，0 为全部 first，1 为全部 second
+#[derive(Clone)]
+pub struct Mix {
+    pub first: Rc<dyn Material>,
+    pub second: Rc<dyn Material>,
+    pub weight: Rc<dyn Texture>,
+}
+
+impl Mix {
+    pub fn new(first: Rc<dyn Material>, second: Rc<dyn Material>, weight: f64) -> Self {
+        Self {
+            first,
+            second,
+            weight: Rc::new(SolidColor::new(weight, weight, weight)),
+        }
+    }
+
+    fn weight(&self, rec: &HitRecord) -> f64 {
+        self.weight
+            .get_filtered_value(rec.u, rec.v, rec.local_p, &rec.footprint)
+            .x
+    }
+}
+
+impl Material for Mix {
+    fn scatter(
+        &self,
+        r_in: Ray,
+        rec: HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        if self.weight(&rec) > sampler.get_1d() {
+            self.second.scatter(r_in, rec, sampler)
+        } else {
+            self.first.scatter(r_in, rec, sampler)
+        }
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Vec3 {
+        let weight = self.weight(rec);
+        self.first.albedo(rec) * (1. - weight) + self.second.albedo(rec) * weight
+    }
+
+    // 按权重混合两边的法向量再归一化；两者几乎相反时取权重大的一边
+    fn shading_normal(&self, rec: &HitRecord) -> Vec3 {
+        let weight = self.weight(rec);
+        let first = self.first.shading_normal(rec);
+        let second = self.second.shading_normal(rec);
+        let blended = first * (1. - weight) + second * weight;
+        if blended.squared_length() > 1e-12 {
+            Vec3::unit(blended)
+        } else if weight > 0.5 {
+            second
+        } else {
+            first
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::{lambertian::Lambertian, tests::hit_unit_sphere};
+
+    // 着色法向量固定为 normal 的测试材质
+    struct Tilted {
+        normal: Vec3,
+    }
+
+    impl Material for Tilted {
+        fn scatter(&self, _: Ray, _: HitRecord, _: &mut dyn Sampler) -> Option<ScatterRecord> {
+            None
+        }
+
+        fn albedo(&self, _: &HitRecord) -> Vec3 {
+            Vec3::zero()
+        }
+
+        fn shading_normal(&self, _: &HitRecord) -> Vec3 {
+            self.normal
+        }
+    }
+
+    // 法向量随权重从 first 过渡到 second
+    #[test]
+    fn shading_normal_follows_the_weight() {
+        let flat = Rc::new(Lambertian {
+            albedo: Rc::new(SolidColor::new(0.5, 0.5, 0.5)),
+        });
+        let tilted = Rc::new(Tilted {
+            normal: Vec3::new(1., 0., 0.),
+        });
+        let (_, rec) = hit_unit_sphere(flat.clone(), Vec3::new(0., 0., 3.), Vec3::new(0., 0., -1.));
+        let normal_at =
+            |weight| Mix::new(flat.clone(), tilted.clone(), weight).shading_normal(&rec);
+        assert!(normal_at(0.) == rec.normal);
+        assert!(normal_at(1.) == tilted.normal);
+        let half = normal_at(0.5);
+        assert!((half - Vec3::unit(Vec3::new(1., 0., 1.))).length() < 1e-12);
+        assert!(normal_at(0.8).x > half.x);
+    }
+
+    // 两边法向量相反时不会得到零向量
+    #[test]
+    fn opposite_normals_pick_the_heavier_side() {
+        let up = Rc::new(Tilted {
+            normal: Vec3::new(0., 0., 1.),
+        });
+        let down = Rc::new(Tilted {
+            normal: Vec3::new(0., 0., -1.),
+        });
+        let (_, rec) = hit_unit_sphere(up.clone(), Vec3::new(0., 0., 3.), Vec3::new(0., 0., -1.));
+        assert!(Mix::new(up.clone(), down.clone(), 0.5).shading_normal(&rec) == up.normal);
+        assert!(Mix::new(up, down.clone(), 0.6).shading_normal(&rec) == down.normal);
+    }
+}
@@ -1,10 +1,20 @@
+pub mod bump_map;
+pub mod coated;
+pub mod cutout;
 pub mod dielectric;
+pub mod isotropic;
 pub mod lambertian;
 pub mod metal;
+pub mod mix;
+pub mod normal_map;
+pub mod oren_nayar;
+pub mod subsurface;
+pub mod thin_film;
 
 use crate::{
     basic::{ray::Ray, vec::Vec3},
     hittable::HitRecord,
+    sampler::Sampler,
 };
 
 pub struct ScatterRecord {
@@ -12,5 +22,67 @@ pub struct ScatterRecord {
     pub scattered: Ray,
 }
 pub trait Material {
-    fn scatter(&self, r_in: Ray, rec: HitRecord) -> Option<ScatterRecord>;
+    fn scatter(
+        &self,
+        r_in: Ray,
+        rec: HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord>;
+
+    // 表面颜色，用于输出 albedo AOV 和引导降噪；玻璃等没有漫反射颜色的材料返回白色
+    fn albedo(&self, _rec: &HitRecord) -> Vec3 {
+        Vec3::ones()
+    }
+
+    // 着色法向量，包括法线贴图和凹凸贴图的扰动
+    fn shading_normal(&self, rec: &HitRecord) -> Vec3 {
+        rec.normal
+    }
+}
+
+// 用扰动后的着色法向量散射。散射方向在着色法向量看来是反射、在几何表面看来却穿到了背面（或反之）时，
+// 把它沿几何切平面镜像回来，避免光线漏到物体内部
+pub fn scatter_with_shading_normal(
+    material: &dyn Material,
+    r_in: Ray,
+    rec: HitRecord,
+    sampler: &mut dyn Sampler,
+) -> Option<ScatterRecord> {
+    let shading_normal = rec.normal;
+    let geometric_normal = rec.geometric_normal;
+    let mut scatter = material.scatter(r_in, rec, sampler)?;
+    let dir = scatter.scattered.dir;
+    if (dir * shading_normal > 0.) != (dir * geometric_normal > 0.) {
+        scatter.scattered.dir = Vec3::reflect(dir, geometric_normal);
+    }
+    Some(scatter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hittable::{sphere::Sphere, Hittable},
+        utility::RandGen,
+    };
+    use rand::SeedableRng;
+    use std::rc::Rc;
+
+    // 从 orig 沿 dir 射向原点处的单位球，返回光线和交点
+    pub(super) fn hit_unit_sphere(
+        material: Rc<dyn Material>,
+        orig: Vec3,
+        dir: Vec3,
+    ) -> (Ray, HitRecord) {
+        let sphere = Sphere {
+            center: Vec3::zero(),
+            r: 1.,
+            mat_ptr: material,
+        };
+        let ray = Ray::new(orig, dir, 0.);
+        let rec = sphere
+            .hit(ray, 0.001, f64::INFINITY, &mut RandGen::seed_from_u64(0))
+            .expect("the ray misses the unit sphere");
+        (ray, rec)
+    }
 }
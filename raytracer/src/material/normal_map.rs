@@ -0,0 +1,129 @@
+use std::rc::Rc;
+
+use super::{scatter_with_shading_normal, Material, ScatterRecord};
+use crate::{
+    basic::{ray::Ray, vec::Vec3},
+    hittable::HitRecord,
+    sampler::Sampler,
+    texture::Texture,
+};
+
+// 切线空间法线贴图，颜色 (r, g, b) 映射为 (2r - 1, 2g - 1, 2b - 1)，分别对应 dp/du、dp/dv 和法向方向
+// 贴图应按数据贴图加载，即不做 sRGB 转换
+#[derive(Clone)]
+pub struct NormalMap {
+    pub material: Rc<dyn Material>,
+    pub map: Rc<dyn Texture>,
+}
+
+impl NormalMap {
+    pub fn perturb(&self, rec: &mut HitRecord) {
+        if rec.dpdu.squared_length() == 0. {
+            return;
+        }
+        let outward = if rec.front_face {
+            rec.normal
+        } else {
+            -rec.normal
+        };
+        let tangent = Vec3::unit(rec.dpdu - outward * (rec.dpdu * outward));
+        let mut bitangent = Vec3::cross(outward, tangent);
+        if bitangent * rec.dpdv < 0. {
+            bitangent = -bitangent;
+        }
+        let c = self
+            .map
+            .get_filtered_value(rec.u, rec.v, rec.local_p, &rec.footprint);
+        let local = c * 2. - 1.;
+        let perturbed = tangent * local.x + bitangent * local.y + outward * local.z;
+        if perturbed.squared_length() == 0. {
+            return;
+        }
+        let perturbed = Vec3::unit(perturbed);
+        rec.normal = if rec.front_face {
+            perturbed
+        } else {
+            -perturbed
+        };
+    }
+}
+
+impl Material for NormalMap {
+    fn scatter(
+        &self,
+        r_in: Ray,
+        mut rec: HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        self.perturb(&mut rec);
+        scatter_with_shading_normal(self.material.as_ref(), r_in, rec, sampler)
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Vec3 {
+        self.material.albedo(rec)
+    }
+
+    fn shading_normal(&self, rec: &HitRecord) -> Vec3 {
+        let mut rec = rec.clone();
+        self.perturb(&mut rec);
+        self.material.shading_normal(&rec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        material::{lambertian::Lambertian, tests::hit_unit_sphere},
+        texture::solid_color::SolidColor,
+    };
+
+    fn mapped(r: f64, g: f64, b: f64, orig: Vec3, dir: Vec3) -> (NormalMap, HitRecord) {
+        let base = Rc::new(Lambertian {
+            albedo: Rc::new(SolidColor::new(0.5, 0.5, 0.5)),
+        });
+        let (_, rec) = hit_unit_sphere(base.clone(), orig, dir);
+        let map = NormalMap {
+            material: base,
+            map: Rc::new(SolidColor::new(r, g, b)),
+        };
+        (map, rec)
+    }
+
+    // (0.5, 0.5, 1) 表示不扰动，从外面和从里面击中都保持原法向量
+    #[test]
+    fn flat_map_keeps_the_normal() {
+        for &(orig, dir) in &[
+            (Vec3::new(0.3, 0.2, 3.), Vec3::new(0., 0., -1.)),
+            (Vec3::zero(), Vec3::new(0.3, 0.2, 1.)),
+        ] {
+            let (map, rec) = mapped(0.5, 0.5, 1., orig, dir);
+            assert!((map.shading_normal(&rec) - rec.normal).length() < 1e-12);
+        }
+    }
+
+    // 红色通道为 1 时法向量转到切线 dp/du 方向，绿色通道对应副切线
+    #[test]
+    fn channels_follow_the_tangent_frame() {
+        let (map, rec) = mapped(
+            1.,
+            0.5,
+            0.5,
+            Vec3::new(0.3, 0.2, 3.),
+            Vec3::new(0., 0., -1.),
+        );
+        let normal = map.shading_normal(&rec);
+        assert!((normal * rec.normal).abs() < 1e-9);
+        assert!((normal * Vec3::unit(rec.dpdu) - 1.).abs() < 1e-9);
+
+        let (map, rec) = mapped(
+            0.5,
+            1.,
+            0.5,
+            Vec3::new(0.3, 0.2, 3.),
+            Vec3::new(0., 0., -1.),
+        );
+        let normal = map.shading_normal(&rec);
+        assert!((normal * rec.normal).abs() < 1e-9 && normal * rec.dpdv > 0.);
+    }
+}
@@ -0,0 +1,136 @@
+use std::rc::Rc;
+
+use super::{Material, ScatterRecord};
+use crate::{
+    basic::{ray::Ray, vec::Vec3},
+    hittable::HitRecord,
+    sampler::Sampler,
+    texture::{solid_color::SolidColor, Texture},
+};
+
+// 粗糙漫反射，sigma 为微表面朝向的标准差（弧度），取纹理的第一个通道；sigma = 0 时退化为 Lambertian
+#[derive(Clone)]
+pub struct OrenNayar {
+    pub albedo: Rc<dyn Texture>,
+    pub sigma: Rc<dyn Texture>,
+}
+
+impl OrenNayar {
+    pub fn new(albedo: Rc<dyn Texture>, sigma: f64) -> Self {
+        Self {
+            albedo,
+            sigma: Rc::new(SolidColor::new(sigma, sigma, sigma)),
+        }
+    }
+
+    // BRDF 相对 Lambertian (albedo / PI) 的比例，wi 指向观察方向，wo 指向出射方向
+    pub fn eval(sigma: f64, wi: Vec3, wo: Vec3, normal: Vec3) -> f64 {
+        let sigma2 = sigma * sigma;
+        let a = 1. - 0.5 * sigma2 / (sigma2 + 0.33);
+        let b = 0.45 * sigma2 / (sigma2 + 0.09);
+
+        let cos_i = (wi * normal).min(1.);
+        let cos_o = (wo * normal).min(1.);
+        if cos_i <= 0. || cos_o <= 0. {
+            return 0.;
+        }
+        let sin_i = (1. - cos_i * cos_i).sqrt();
+        let sin_o = (1. - cos_o * cos_o).sqrt();
+
+        // 两方向投影到切平面后夹角的余弦
+        let mut cos_phi = 0.;
+        if sin_i > 1e-4 && sin_o > 1e-4 {
+            let ti = (wi - normal * cos_i) / sin_i;
+            let to = (wo - normal * cos_o) / sin_o;
+            cos_phi = (ti * to).max(0.);
+        }
+
+        // alpha = max(theta_i, theta_o), beta = min(theta_i, theta_o)
+        let (sin_alpha, tan_beta) = if cos_i > cos_o {
+            (sin_o, sin_i / cos_i)
+        } else {
+            (sin_i, sin_o / cos_o)
+        };
+        a + b * cos_phi * sin_alpha * tan_beta
+    }
+}
+
+impl Material for OrenNayar {
+    fn scatter(
+        &self,
+        r_in: Ray,
+        rec: HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        // 与 Lambertian 一样按余弦分布采样，BRDF 与余弦分布的比值放进 attenuation
+        let scatter_direction = rec.normal + Vec3::unit_sphere_from_square(sampler.get_2d());
+        if scatter_direction.squared_length() < 1e-16 {
+            return None;
+        }
+        let sigma = self
+            .sigma
+            .get_filtered_value(rec.u, rec.v, rec.local_p, &rec.footprint)
+            .x;
+        let weight = OrenNayar::eval(
+            sigma,
+            -Vec3::unit(r_in.dir),
+            Vec3::unit(scatter_direction),
+            rec.normal,
+        );
+        Some(ScatterRecord {
+            scattered: Ray::new(rec.p, scatter_direction, r_in.time),
+            attenuation: self
+                .albedo
+                .get_filtered_value(rec.u, rec.v, rec.local_p, &rec.footprint)
+                * weight,
+        })
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Vec3 {
+        self.albedo
+            .get_filtered_value(rec.u, rec.v, rec.local_p, &rec.footprint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        material::{lambertian::Lambertian, tests::hit_unit_sphere},
+        sampler::independent::IndependentSampler,
+    };
+
+    // sigma = 0 时与 Lambertian 完全相同：同样的样本给出同样的方向和颜色
+    #[test]
+    fn smooth_oren_nayar_is_lambertian() {
+        let albedo: Rc<dyn Texture> = Rc::new(SolidColor::new(0.2, 0.5, 0.8));
+        let rough = OrenNayar::new(albedo.clone(), 0.);
+        let lambertian = Rc::new(Lambertian { albedo });
+        let (ray, rec) = hit_unit_sphere(
+            lambertian.clone(),
+            Vec3::new(0.3, 0.4, 3.),
+            Vec3::new(0., 0., -1.),
+        );
+        let mut a = IndependentSampler::new(5);
+        let mut b = IndependentSampler::new(5);
+        for i in 0..1000 {
+            a.start_sample(0, i);
+            b.start_sample(0, i);
+            let expected = lambertian.scatter(ray, rec.clone(), &mut a).unwrap();
+            let scatter = rough.scatter(ray, rec.clone(), &mut b).unwrap();
+            assert!(scatter.scattered.dir == expected.scattered.dir);
+            assert!(scatter.attenuation == expected.attenuation);
+        }
+    }
+
+    // 粗糙表面正对着看更暗，掠射时朝光源方向反射回来的比镜面方向多
+    #[test]
+    fn rough_surface_scatters_back() {
+        let normal = Vec3::new(0., 0., 1.);
+        let wi = Vec3::unit(Vec3::new(1., 0., 0.5));
+        let mirrored = Vec3::unit(Vec3::new(-1., 0., 0.5));
+        assert!(OrenNayar::eval(0.5, normal, normal, normal) < 1.);
+        assert!(OrenNayar::eval(0.5, wi, wi, normal) > OrenNayar::eval(0.5, wi, mirrored, normal));
+        assert!(OrenNayar::eval(0.5, wi, -wi, normal) == 0.);
+    }
+}
@@ -0,0 +1,53 @@
+use std::rc::Rc;
+
+use super::{dielectric::Dielectric, isotropic::Isotropic, Material, ScatterRecord};
+use crate::{
+    basic::{ray::Ray, vec::Vec3},
+    hittable::HitRecord,
+    sampler::Sampler,
+    texture::Texture,
+};
+
+// 次表面散射材质（皮肤、蜡、大理石）
+// 表面按 Dielectric 处理反射和折射，进入物体后的随机游走由 hittable::subsurface::SubsurfaceVolume 完成
+#[derive(Clone)]
+pub struct Subsurface {
+    pub boundary: Dielectric,
+    pub albedo: Rc<dyn Texture>, // 每次体内散射后剩下的比例
+    pub mean_free_path: f64,     // 两次体内散射之间的平均距离
+}
+
+impl Subsurface {
+    pub fn new(ref_idx: f64, albedo: Rc<dyn Texture>, mean_free_path: f64) -> Self {
+        Self {
+            boundary: Dielectric {
+                ref_idx,
+                film: None,
+            },
+            albedo,
+            mean_free_path,
+        }
+    }
+
+    pub fn phase_function(&self) -> Isotropic {
+        Isotropic {
+            albedo: self.albedo.clone(),
+        }
+    }
+}
+
+impl Material for Subsurface {
+    fn scatter(
+        &self,
+        r_in: Ray,
+        rec: HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        self.boundary.scatter(r_in, rec, sampler)
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Vec3 {
+        self.albedo
+            .get_filtered_value(rec.u, rec.v, rec.local_p, &rec.footprint)
+    }
+}
@@ -0,0 +1,135 @@
+use std::{f64::consts::PI, rc::Rc};
+
+use crate::{
+    basic::vec::Vec3,
+    hittable::HitRecord,
+    texture::{solid_color::SolidColor, Texture},
+    utility,
+};
+
+// 每个颜色通道取一个代表波长 (nm)
+const WAVELENGTHS: [f64; 3] = [650., 532., 450.];
+
+#[derive(Clone)]
+pub struct ThinFilm {
+    pub thickness: Rc<dyn Texture>, // 膜厚，单位 nm，取纹理的第一个通道
+    pub ior: f64,
+}
+
+impl ThinFilm {
+    pub fn new(thickness: f64, ior: f64) -> Self {
+        Self {
+            thickness: Rc::new(SolidColor::new(thickness, thickness, thickness)),
+            ior,
+        }
+    }
+
+    // 光从折射率 eta_i 的介质经过薄膜射向折射率 eta_t 的基底，返回每个通道的反射率
+    // 薄膜内多次反射的干涉用 Airy 公式求和，s/p 偏振取平均
+    pub fn reflectance(&self, cos_i: f64, eta_i: f64, eta_t: Vec3, rec: &HitRecord) -> Vec3 {
+        let thickness = self
+            .thickness
+            .get_filtered_value(rec.u, rec.v, rec.local_p, &rec.footprint)
+            .x
+            .max(0.);
+        let sin2_i = 1. - cos_i * cos_i;
+        let sin2_f = (eta_i / self.ior).powi(2) * sin2_i;
+        if sin2_f >= 1. {
+            return Vec3::ones(); // 在薄膜上表面全反射
+        }
+        let cos_f = (1. - sin2_f).sqrt();
+        let r12_s = (eta_i * cos_i - self.ior * cos_f) / (eta_i * cos_i + self.ior * cos_f);
+        let r12_p = (self.ior * cos_i - eta_i * cos_f) / (self.ior * cos_i + eta_i * cos_f);
+
+        let mut result = Vec3::zero();
+        for c in 0..3 {
+            let sin2_t = (eta_i / eta_t[c]).powi(2) * sin2_i;
+            if sin2_t >= 1. {
+                // 基底处全反射，薄膜无吸收，能量最终全部反射回去
+                result[c] = 1.;
+                continue;
+            }
+            let cos_t = (1. - sin2_t).sqrt();
+            let r23_s =
+                (self.ior * cos_f - eta_t[c] * cos_t) / (self.ior * cos_f + eta_t[c] * cos_t);
+            let r23_p =
+                (eta_t[c] * cos_f - self.ior * cos_t) / (eta_t[c] * cos_f + self.ior * cos_t);
+            let phase = 4. * PI * self.ior * thickness * cos_f / WAVELENGTHS[c];
+            result[c] = 0.5 * (airy(r12_s, r23_s, phase) + airy(r12_p, r23_p, phase));
+        }
+        result
+    }
+
+    // 由金属的法向反射率 F0 反推等效的实折射率，用于给金属基底套薄膜
+    pub fn ior_from_f0(f0: Vec3) -> Vec3 {
+        let mut ior = Vec3::zero();
+        for c in 0..3 {
+            let r = utility::clamp(f0[c], 0., 0.999).sqrt();
+            ior[c] = (1. + r) / (1. - r);
+        }
+        ior
+    }
+}
+
+fn airy(r12: f64, r23: f64, phase: f64) -> f64 {
+    let cross = 2. * r12 * r23 * phase.cos();
+    (r12 * r12 + r23 * r23 + cross) / (1. + r12 * r12 * r23 * r23 + cross)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::{dielectric::Dielectric, tests::hit_unit_sphere};
+
+    // 从折射率 eta_i 射向 eta_t 的精确 Fresnel 反射率，s/p 偏振取平均
+    fn fresnel(cos_i: f64, eta_i: f64, eta_t: f64) -> f64 {
+        let sin_t = eta_i / eta_t * (1. - cos_i * cos_i).sqrt();
+        let cos_t = (1. - sin_t * sin_t).sqrt();
+        let rs = (eta_i * cos_i - eta_t * cos_t) / (eta_i * cos_i + eta_t * cos_t);
+        let rp = (eta_t * cos_i - eta_i * cos_t) / (eta_t * cos_i + eta_i * cos_t);
+        0.5 * (rs * rs + rp * rp)
+    }
+
+    fn glass_hit() -> HitRecord {
+        let glass = Rc::new(Dielectric {
+            ref_idx: 1.5,
+            film: None,
+        });
+        hit_unit_sphere(glass, Vec3::new(0., 0., 3.), Vec3::new(0., 0., -1.)).1
+    }
+
+    // 厚度为 0 的膜不起作用，反射率就是玻璃本身的 Fresnel 反射率，与膜的折射率无关
+    #[test]
+    fn zero_thickness_is_plain_fresnel() {
+        let rec = glass_hit();
+        for &ior in &[1.33, 2.] {
+            let film = ThinFilm::new(0., ior);
+            for &cos in &[1., 0.8, 0.5, 0.2] {
+                let r = film.reflectance(cos, 1., Vec3::ones() * 1.5, &rec);
+                for c in 0..3 {
+                    assert!(
+                        (r[c] - fresnel(cos, 1., 1.5)).abs() < 1e-12,
+                        "{} {}",
+                        ior,
+                        cos
+                    );
+                }
+            }
+        }
+        // 垂直入射时与 Dielectric 的 Schlick 近似一致
+        let r = ThinFilm::new(0., 1.33).reflectance(1., 1., Vec3::ones() * 1.5, &rec);
+        assert!((r.x - Dielectric::reflectance(1., 1.5)).abs() < 1e-12);
+    }
+
+    // 有厚度时三个通道的干涉相位不同，反射出彩色，反射率仍在 [0, 1] 内
+    #[test]
+    fn soap_film_is_coloured() {
+        let rec = glass_hit();
+        let r = ThinFilm::new(400., 1.33).reflectance(0.9, 1., Vec3::ones(), &rec);
+        for c in 0..3 {
+            assert!(r[c] >= 0. && r[c] <= 1.);
+        }
+        let spread = r.x.max(r.y).max(r.z) - r.x.min(r.y).min(r.z);
+        assert!(spread > 0.01, "{} {} {}", r.x, r.y, r.z);
+    }
+}
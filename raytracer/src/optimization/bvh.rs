@@ -1,10 +1,12 @@
 #![allow(dead_code)]
 use crate::{
     basic::ray::Ray,
-    hittable::{HitRecord, Hittable, HittableList},
+    hittable::{HitRecord, Hittable, HittableList, ObjectId},
+    material::Material,
     optimization::aabb::AABB,
     utility,
 };
+use rand::{Rng, RngCore};
 use std::cmp::Ordering;
 use std::rc::Rc;
 #[derive(Clone)]
@@ -15,17 +17,17 @@ pub struct BvhNode {
 }
 
 impl Hittable for BvhNode {
-    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64, rng: &mut dyn RngCore) -> Option<HitRecord> {
         if !self.ab_box.hit(r, t_min, t_max) {
             return None;
         }
         let mut hit_rec = None;
         let mut closest_so_far = t_max;
-        if let Some(hitleft) = self.left.hit(r, t_min, t_max) {
+        if let Some(hitleft) = self.left.hit(r, t_min, t_max, rng) {
             closest_so_far = hitleft.t;
             hit_rec = Some(hitleft);
         }
-        if let Some(hitright) = self.right.hit(r, t_min, closest_so_far) {
+        if let Some(hitright) = self.right.hit(r, t_min, closest_so_far, rng) {
             hit_rec = Some(hitright);
         }
         hit_rec
@@ -34,6 +36,11 @@ impl Hittable for BvhNode {
     fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB> {
         Some(self.ab_box)
     }
+
+    fn materials(&self, list: &mut Vec<Rc<dyn Material>>) {
+        self.left.materials(list);
+        self.right.materials(list);
+    }
 }
 
 impl BvhNode {
@@ -67,24 +74,47 @@ impl BvhNode {
         BvhNode::box_cmp(a, b, 2)
     }
 
-    pub fn new_from_vec(src_objects: &[Rc<dyn Hittable>], time0: f64, time1: f64) -> Self {
+    pub fn new_from_vec<R: Rng + ?Sized>(
+        src_objects: &[Rc<dyn Hittable>],
+        time0: f64,
+        time1: f64,
+        rng: &mut R,
+    ) -> Self {
         let x = src_objects.len(); // &Vec<Rc<dyn Hittable>>
-        BvhNode::new_with_5para(src_objects, 0, x, time0, time1)
+        BvhNode::new_with_5para(src_objects, 0, x, time0, time1, rng)
     }
 
-    pub fn new_from_list(list: HittableList, time0: f64, time1: f64) -> Self {
-        BvhNode::new_from_vec(&list.objects, time0, time1)
+    pub fn new_from_list<R: Rng + ?Sized>(
+        list: HittableList,
+        time0: f64,
+        time1: f64,
+        rng: &mut R,
+    ) -> Self {
+        // 物体编号与直接用列表渲染时相同
+        let objects: Vec<Rc<dyn Hittable>> = list
+            .objects
+            .iter()
+            .enumerate()
+            .map(|(index, object)| {
+                Rc::new(ObjectId {
+                    object: object.clone(),
+                    id: index as u32 + 1,
+                }) as Rc<dyn Hittable>
+            })
+            .collect();
+        BvhNode::new_from_vec(&objects, time0, time1, rng)
     }
 
-    pub fn new_with_5para(
+    pub fn new_with_5para<R: Rng + ?Sized>(
         src_objects: &[Rc<dyn Hittable>], // &Vec<Rc<dyn Hittable>>
         start: usize,
         end: usize,
         time0: f64,
         time1: f64,
+        rng: &mut R,
     ) -> Self {
         let mut objects = src_objects[start..end].to_vec();
-        let axis = utility::random_int(0, 2);
+        let axis = utility::random_int(rng, 0, 2);
         let comparator = if axis == 0 {
             BvhNode::x_cmp
         } else if axis == 1 {
@@ -117,8 +147,16 @@ impl BvhNode {
                 mid,
                 time0,
                 time1,
+                rng,
+            ));
+            right = Rc::new(BvhNode::new_with_5para(
+                src_objects,
+                mid,
+                end,
+                time0,
+                time1,
+                rng,
             ));
-            right = Rc::new(BvhNode::new_with_5para(src_objects, mid, end, time0, time1));
             let mut flag1 = false;
             let mut flag2 = false;
             if let Some(_box_a) = left.bounding_box(time0, time1) {
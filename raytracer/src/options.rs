@@ -0,0 +1,269 @@
+use std::time::Duration;
+
+use crate::{
+    render::{
+        aov::AovKind,
+        film::PixelFilter,
+        tonemap::{DisplayTransform, ToneMapOperator},
+    },
+    utility,
+};
+
+pub const USAGE: &str = "Usage:
+  raytracer [options]                      render the scene
+  raytracer tonemap <input.exr> [options]  apply the display transform to a saved EXR
+  raytracer denoise <input.exr> [options]  denoise a saved EXR, guided by its AOV layers
+  raytracer animate <first>..<last> [options]
+                                           render frames first to last of the animation; a run of
+                                           # in the output path becomes the frame number
+Options:
+  -o, --output <path>        output file, format chosen by extension (default output/output.jpg)
+  --quality <0-100>          JPEG quality
+  --resume                   continue from the checkpoint
+  --checkpoint <path>        checkpoint file (default output/checkpoint.bin)
+  --time-limit <seconds>     stop rendering after this much time
+  --target-noise <value>     stop when the mean relative error drops below this
+  --crop <x0,y0,x1,y1>       render only this pixel rectangle (origin top left, end exclusive)
+  --crop-window <x0,y0,x1,y1>  the same in normalized [0, 1] coordinates
+  --overlay <image>          paste the cropped render onto this earlier full image instead of
+                             writing a cropped image (must be EXR for .exr/.hdr output)
+  --fps <value>              animation frame rate (default 24)
+  --shutter <fraction>       part of each frame the shutter is open, for motion blur (default 0.5)
+  --seed <value>             random seed; the same seed renders a bit-identical image (default 0)
+  --scene <name>             scene to render: two-spheres, random or materials (default two-spheres)
+  --filter <name[:radius]>   pixel filter: box, tent, gaussian, mitchell or lanczos (default box:0.5)
+  --exposure <EV>            exposure compensation
+  --white-balance <kelvin>   color temperature that should appear white
+  --tonemap <operator>       clamp, reinhard, reinhard-extended[:white], hable, aces or agx
+  --aovs <list>              comma-separated AOVs or \"all\": albedo, normal, depth, position, uv,
+                             object_id, material_id, motion; EXR layers or <output>.<aov>.exr
+  --denoise                  denoise the render using albedo, normal and depth AOVs";
+
+// 渲染哪个静止场景，animate 总是渲染 two-spheres 的动画版本
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Scene {
+    TwoSpheres,
+    Random,
+    Materials, // 各种材质和纹理的样例
+}
+
+impl Scene {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "two-spheres" => Some(Scene::TwoSpheres),
+            "random" => Some(Scene::Random),
+            "materials" => Some(Scene::Materials),
+            _ => None,
+        }
+    }
+}
+
+pub enum Command {
+    Render,
+    Tonemap { input: String },
+    Denoise { input: String },
+    Animate { first: u32, last: u32 },
+}
+
+// 命令行参数，没有给出的项使用 main 里写死的设置
+pub struct Options {
+    pub command: Command,
+    pub output: String, // 按扩展名选择格式：png、ppm、bmp、tga、jpg、exr、hdr
+    pub quality: u8,    // JPEG 质量
+    pub resume: bool,
+    pub checkpoint: String,
+    pub time_limit: Option<Duration>,
+    pub target_noise: Option<f64>,
+    pub filter: Option<PixelFilter>,
+    pub crop: Option<[f64; 4]>, // x0, y0, x1, y1
+    pub crop_normalized: bool,  // crop 是 [0, 1] 内的坐标
+    pub overlay: Option<String>,
+    pub fps: f64,
+    pub shutter: f64,
+    pub seed: u64,
+    pub scene: Scene,
+    pub display: DisplayTransform, // 只影响 8 位输出
+    pub aovs: Vec<AovKind>,
+    pub denoise: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            command: Command::Render,
+            output: "output/output.jpg".to_string(),
+            quality: 60,
+            resume: false,
+            checkpoint: "output/checkpoint.bin".to_string(),
+            time_limit: None,
+            target_noise: None,
+            filter: None,
+            crop: None,
+            crop_normalized: false,
+            overlay: None,
+            fps: 24.,
+            shutter: 0.5,
+            seed: 0,
+            scene: Scene::TwoSpheres,
+            display: DisplayTransform::default(),
+            aovs: Vec::new(),
+            denoise: false,
+        }
+    }
+}
+
+impl Options {
+    pub fn parse<I: Iterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut options = Options::default();
+        let mut args = args.peekable();
+        match args.peek().map(|a| a.as_str()) {
+            Some("tonemap") | Some("denoise") => {
+                let command = args.next().unwrap_or_default();
+                let input = args
+                    .next()
+                    .ok_or_else(|| format!("missing input file for {}", command))?;
+                options.command = if command == "tonemap" {
+                    Command::Tonemap { input }
+                } else {
+                    Command::Denoise { input }
+                };
+            }
+            Some("animate") => {
+                args.next();
+                let range = args
+                    .next()
+                    .ok_or_else(|| "missing frame range for animate".to_string())?;
+                let (first, last) = frame_range(&range)
+                    .ok_or_else(|| format!("invalid frame range \"{}\"", range))?;
+                options.command = Command::Animate { first, last };
+            }
+            _ => {}
+        }
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--output" | "-o" => options.output = value(&arg, args.next())?,
+                "--quality" => {
+                    let quality: u8 = number(&arg, args.next())?;
+                    options.quality = quality.min(100);
+                }
+                "--resume" => options.resume = true,
+                "--checkpoint" => options.checkpoint = value(&arg, args.next())?,
+                "--time-limit" => {
+                    let seconds: f64 = number(&arg, args.next())?;
+                    // NaN、无穷大和超出范围的值会让 Duration::from_secs_f64 panic
+                    if !seconds.is_finite() || seconds < 0. || seconds >= u64::MAX as f64 {
+                        return Err(
+                            "--time-limit must be a non-negative number of seconds".to_string()
+                        );
+                    }
+                    options.time_limit = Some(Duration::from_secs_f64(seconds));
+                }
+                "--target-noise" => options.target_noise = Some(number(&arg, args.next())?),
+                "--crop" | "--crop-window" => {
+                    options.crop = Some(bounds(&arg, args.next())?);
+                    options.crop_normalized = arg == "--crop-window";
+                }
+                "--overlay" => options.overlay = Some(value(&arg, args.next())?),
+                "--fps" => {
+                    options.fps = number(&arg, args.next())?;
+                    if !options.fps.is_finite() || options.fps <= 0. {
+                        return Err("--fps must be positive".to_string());
+                    }
+                }
+                "--shutter" => {
+                    let shutter: f64 = number(&arg, args.next())?;
+                    options.shutter = utility::clamp(shutter, 0., 1.);
+                }
+                "--seed" => options.seed = number(&arg, args.next())?,
+                "--scene" => {
+                    let name = value(&arg, args.next())?;
+                    options.scene = Scene::from_name(&name)
+                        .ok_or_else(|| format!("unknown scene \"{}\"", name))?;
+                }
+                "--filter" => {
+                    let name = value(&arg, args.next())?;
+                    options.filter = Some(
+                        PixelFilter::from_name(&name)
+                            .ok_or_else(|| format!("unknown pixel filter \"{}\"", name))?,
+                    );
+                }
+                "--exposure" => options.display.exposure = number(&arg, args.next())?,
+                "--white-balance" => {
+                    let kelvin = number(&arg, args.next())?;
+                    options.display.white_balance =
+                        DisplayTransform::white_balance_from_temperature(kelvin);
+                }
+                "--tonemap" => {
+                    let name = value(&arg, args.next())?;
+                    options.display.operator = ToneMapOperator::from_name(&name)
+                        .ok_or_else(|| format!("unknown tone mapping operator \"{}\"", name))?;
+                }
+                "--aovs" => {
+                    let list = value(&arg, args.next())?;
+                    options.aovs = AovKind::parse_list(&list)
+                        .ok_or_else(|| format!("unknown AOV in \"{}\"", list))?;
+                }
+                "--denoise" => options.denoise = true,
+                _ => return Err(format!("unknown argument \"{}\"", arg)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+fn value(flag: &str, next: Option<String>) -> Result<String, String> {
+    next.ok_or_else(|| format!("missing value for {}", flag))
+}
+
+fn number<T: std::str::FromStr>(flag: &str, next: Option<String>) -> Result<T, String> {
+    let text = value(flag, next)?;
+    text.parse()
+        .map_err(|_| format!("invalid value \"{}\" for {}", text, flag))
+}
+
+// 逗号分隔的四个数
+fn bounds(flag: &str, next: Option<String>) -> Result<[f64; 4], String> {
+    let text = value(flag, next)?;
+    let values: Vec<f64> = text
+        .split(',')
+        .map(|v| v.trim().parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("invalid value \"{}\" for {}", text, flag))?;
+    if values.len() != 4 {
+        return Err(format!("{} needs four values x0,y0,x1,y1", flag));
+    }
+    Ok([values[0], values[1], values[2], values[3]])
+}
+
+// "3..10" 或单独一帧 "3"，包含两端
+fn frame_range(text: &str) -> Option<(u32, u32)> {
+    let (first, last) = match text.find("..") {
+        Some(i) => (text[..i].parse().ok()?, text[i + 2..].parse().ok()?),
+        None => {
+            let frame = text.parse().ok()?;
+            (frame, frame)
+        }
+    };
+    if first > last {
+        return None;
+    }
+    Some((first, last))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn time_limit_must_be_a_finite_duration() {
+        let options = parse(&["--time-limit", "2.5"]).unwrap();
+        assert_eq!(options.time_limit, Some(Duration::from_millis(2500)));
+        for &bad in &["inf", "-inf", "NaN", "-1", "1e30", "soon"] {
+            assert!(parse(&["--time-limit", bad]).is_err(), "{}", bad);
+        }
+    }
+}
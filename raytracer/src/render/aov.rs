@@ -0,0 +1,231 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{
+    basic::{camera::Camera, vec::Vec3},
+    hittable::Hittable,
+    material::Material,
+    render::{exr::ExrChannel, CropWindow},
+    sampler::{independent::IndependentSampler, Sampler},
+    utility,
+};
+
+// 每个像素用 AOV_GRID x AOV_GRID 条光线求平均，编号和运动矢量取像素中心的光线
+const AOV_GRID: u32 = 4;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AovKind {
+    Albedo,
+    Normal,
+    Depth,
+    Position,
+    Uv,
+    ObjectId,
+    MaterialId,
+    Motion,
+}
+
+impl AovKind {
+    pub const ALL: [AovKind; 8] = [
+        AovKind::Albedo,
+        AovKind::Normal,
+        AovKind::Depth,
+        AovKind::Position,
+        AovKind::Uv,
+        AovKind::ObjectId,
+        AovKind::MaterialId,
+        AovKind::Motion,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AovKind::Albedo => "albedo",
+            AovKind::Normal => "normal",
+            AovKind::Depth => "depth",
+            AovKind::Position => "position",
+            AovKind::Uv => "uv",
+            AovKind::ObjectId => "object_id",
+            AovKind::MaterialId => "material_id",
+            AovKind::Motion => "motion",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        AovKind::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.name() == name)
+    }
+
+    // 逗号分隔的列表，或者 "all"
+    pub fn parse_list(list: &str) -> Option<Vec<Self>> {
+        if list == "all" {
+            return Some(AovKind::ALL.to_vec());
+        }
+        list.split(',')
+            .map(|name| AovKind::from_name(name.trim()))
+            .collect()
+    }
+}
+
+// 第一次相交处的各种量，按图片的行序从上到下存放；没有击中物体的像素全部为 0
+pub struct AovBuffers {
+    pub width: u32,
+    pub height: u32,
+    pub albedo: Vec<Vec3>,
+    pub normal: Vec<Vec3>,   // 世界空间的着色法向量
+    pub depth: Vec<f64>,     // 沿相机朝向的距离
+    pub position: Vec<Vec3>, // 世界空间坐标
+    pub uv: Vec<(f64, f64)>,
+    pub object_id: Vec<u32>,
+    pub material_id: Vec<u32>, // 按材质在场景里第一次出现的顺序编号，与渲染的区域和帧无关
+    pub motion: Vec<(f64, f64)>, // 快门时间内在图片上移动的像素数，y 向下
+}
+
+impl AovBuffers {
+    pub fn new(width: u32, height: u32) -> Self {
+        let n = (width * height) as usize;
+        Self {
+            width,
+            height,
+            albedo: vec![Vec3::zero(); n],
+            normal: vec![Vec3::zero(); n],
+            depth: vec![0.; n],
+            position: vec![Vec3::zero(); n],
+            uv: vec![(0., 0.); n],
+            object_id: vec![0; n],
+            material_id: vec![0; n],
+            motion: vec![(0., 0.); n],
+        }
+    }
+
+    // EXR 通道，通道名以 AOV 的名字为层名
+    pub fn channels(&self, kind: AovKind) -> Vec<ExrChannel> {
+        let layer =
+            |c: &str, data: Vec<f32>| ExrChannel::new(&format!("{}.{}", kind.name(), c), data);
+        let vec3 = |buffer: &[Vec3], names: [&str; 3]| {
+            (0..3)
+                .map(|c| layer(names[c], buffer.iter().map(|v| v[c] as f32).collect()))
+                .collect()
+        };
+        let pair = |buffer: &[(f64, f64)], names: [&str; 2]| {
+            vec![
+                layer(names[0], buffer.iter().map(|v| v.0 as f32).collect()),
+                layer(names[1], buffer.iter().map(|v| v.1 as f32).collect()),
+            ]
+        };
+        match kind {
+            AovKind::Albedo => vec3(&self.albedo, ["R", "G", "B"]),
+            AovKind::Normal => vec3(&self.normal, ["X", "Y", "Z"]),
+            AovKind::Depth => vec![layer("Z", self.depth.iter().map(|v| *v as f32).collect())],
+            AovKind::Position => vec3(&self.position, ["X", "Y", "Z"]),
+            AovKind::Uv => pair(&self.uv, ["U", "V"]),
+            AovKind::ObjectId => vec![layer(
+                "id",
+                self.object_id.iter().map(|v| *v as f32).collect(),
+            )],
+            AovKind::MaterialId => vec![layer(
+                "id",
+                self.material_id.iter().map(|v| *v as f32).collect(),
+            )],
+            AovKind::Motion => pair(&self.motion, ["X", "Y"]),
+        }
+    }
+}
+
+// 只求第一次相交，不做光线追踪；随机数与渲染本身分开，不影响渲染结果
+// 只计算 window 内的像素，结果的大小与 window 相同
+pub fn render_aovs<T: Hittable>(
+    world: &T,
+    camera: Camera,
+    width: u32,
+    height: u32,
+    window: CropWindow,
+    seed: u64,
+) -> AovBuffers {
+    let mut aovs = AovBuffers::new(window.width(), window.height());
+    let mut sampler = IndependentSampler::new(utility::hash_u64(seed ^ 0x414f_5600));
+    let key = |material: &Rc<dyn Material>| Rc::as_ptr(material) as *const () as usize;
+    let mut scene_materials = Vec::new();
+    world.materials(&mut scene_materials);
+    let mut materials: HashMap<usize, u32> = HashMap::new();
+    for material in &scene_materials {
+        let next_id = materials.len() as u32 + 1;
+        materials.entry(key(material)).or_insert(next_id);
+    }
+    let weight = 1. / (AOV_GRID * AOV_GRID) as f64;
+    for row in window.y0..window.y1 {
+        let y = height - row - 1;
+        for x in window.x0..window.x1 {
+            let index = ((row - window.y0) * window.width() + x - window.x0) as usize;
+            let pixel = (y * width + x) as u64;
+            for k in 0..AOV_GRID * AOV_GRID {
+                sampler.start_sample(pixel, k as u64 + 1);
+                let u = (x as f64 + ((k % AOV_GRID) as f64 + 0.5) / AOV_GRID as f64) / width as f64;
+                let v =
+                    (y as f64 + ((k / AOV_GRID) as f64 + 0.5) / AOV_GRID as f64) / height as f64;
+                let mut ray = Camera::get_ray_differential(
+                    camera,
+                    u,
+                    v,
+                    1. / width as f64,
+                    1. / height as f64,
+                    &mut sampler,
+                );
+                ray.scale_differentials(1. / AOV_GRID as f64);
+                if let Some(mut rec) = world.hit(ray, 0.001, f64::INFINITY, sampler.rng()) {
+                    rec.compute_footprint(&ray);
+                    aovs.albedo[index] += rec.mat_ptr.albedo(&rec) * weight;
+                    aovs.normal[index] += rec.mat_ptr.shading_normal(&rec) * weight;
+                    aovs.depth[index] += (rec.p - camera.origin) * -camera.w * weight;
+                    aovs.position[index] += rec.p * weight;
+                    aovs.uv[index].0 += rec.u * weight;
+                    aovs.uv[index].1 += rec.v * weight;
+                }
+            }
+
+            // 像素中心
+            sampler.start_sample(pixel, 0);
+            let ray = Camera::get_ray(
+                camera,
+                (x as f64 + 0.5) / width as f64,
+                (y as f64 + 0.5) / height as f64,
+                &mut sampler,
+            );
+            if let Some(rec) = world.hit(ray, 0.001, f64::INFINITY, sampler.rng()) {
+                aovs.object_id[index] = rec.object_id;
+                aovs.material_id[index] = materials.get(&key(&rec.mat_ptr)).copied().unwrap_or(0);
+                let p0 = rec.p - rec.velocity * (ray.time - camera.time0);
+                let p1 = rec.p + rec.velocity * (camera.time1 - ray.time);
+                if let (Some(s0), Some(s1)) = (camera.project(p0), camera.project(p1)) {
+                    aovs.motion[index] =
+                        ((s1.0 - s0.0) * width as f64, -(s1.1 - s0.1) * height as f64);
+                }
+            }
+        }
+    }
+    aovs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::tests::{tiny_camera, tiny_scene};
+
+    // 地面是场景里的第一个材质，玻璃球是第二个；只渲染球所在的区域时球的编号也不变
+    #[test]
+    fn material_ids_follow_the_scene_order() {
+        let world = tiny_scene();
+        let full = render_aovs(&world, tiny_camera(), 12, 8, CropWindow::full(12, 8), 1);
+        assert_eq!(full.material_id[0], 0);
+        assert_eq!(*full.material_id.iter().max().unwrap(), 2);
+        assert!(full.material_id.contains(&1));
+        let centre = CropWindow {
+            x0: 5,
+            y0: 3,
+            x1: 7,
+            y1: 5,
+        };
+        let cropped = render_aovs(&world, tiny_camera(), 12, 8, centre, 1);
+        assert_eq!(cropped.material_id, vec![2; 4]);
+    }
+}
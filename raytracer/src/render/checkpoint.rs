@@ -0,0 +1,282 @@
+use std::{
+    convert::TryInto,
+    fs,
+    io::{self, Error, ErrorKind},
+    time::Duration,
+};
+
+use crate::{
+    basic::{camera::Camera, vec::Vec3},
+    render::{
+        film::{FilmPixel, FilterKind, PixelFilter},
+        CropWindow, PixelStats, RenderSettings, Renderer,
+    },
+    sampler::SamplerKind,
+};
+
+// 检查点文件：设置、已渲染时间、每个像素的累加值和 film，全部按小端序逐位保存
+// 每个样本的随机数只由种子、像素和样本编号决定，所以不需要保存随机数生成器的内部状态，
+// 恢复后接着往下采样，得到的结果与不中断的渲染逐位相同
+// （滤波半径超过半个像素时，相邻像素的样本累加顺序可能不同，只在舍入误差内一致）
+const MAGIC: &[u8; 8] = b"RTCKPT03";
+
+pub fn save(renderer: &Renderer, path: &str) -> io::Result<()> {
+    // 先写临时文件再改名，写到一半断电也不会破坏上一个检查点
+    let tmp_path = format!("{}.tmp", path);
+    fs::write(&tmp_path, &encode(renderer))?;
+    fs::rename(&tmp_path, path)
+}
+
+// 每个像素的统计占 36 字节，film 占 32 字节
+const BYTES_PER_PIXEL: usize = 68;
+
+fn encode(renderer: &Renderer) -> Vec<u8> {
+    let settings = &renderer.settings;
+    let mut data = Vec::with_capacity(96 + renderer.stats.len() * BYTES_PER_PIXEL);
+    data.extend_from_slice(MAGIC);
+    data.extend_from_slice(&settings.width.to_le_bytes());
+    data.extend_from_slice(&settings.height.to_le_bytes());
+    data.extend_from_slice(&settings.samples_per_pixel.to_le_bytes());
+    data.extend_from_slice(&settings.max_depth.to_le_bytes());
+    data.extend_from_slice(&settings.seed.to_le_bytes());
+    data.push(sampler_id(settings.sampler));
+    data.extend_from_slice(&settings.error_threshold.to_le_bytes());
+    data.extend_from_slice(&settings.min_samples.to_le_bytes());
+    data.extend_from_slice(&settings.batch_samples.to_le_bytes());
+    let time_limit = settings.time_limit.map_or(-1., |t| t.as_secs_f64());
+    data.extend_from_slice(&time_limit.to_le_bytes());
+    data.extend_from_slice(&settings.target_noise.to_le_bytes());
+    data.push(filter_id(settings.filter.kind));
+    data.extend_from_slice(&settings.filter.radius.to_le_bytes());
+    for v in &[
+        settings.crop.x0,
+        settings.crop.y0,
+        settings.crop.x1,
+        settings.crop.y1,
+    ] {
+        data.extend_from_slice(&v.to_le_bytes());
+    }
+    data.extend_from_slice(&renderer.elapsed().as_secs_f64().to_le_bytes());
+    for stats in &renderer.stats {
+        data.extend_from_slice(&stats.sum.x.to_le_bytes());
+        data.extend_from_slice(&stats.sum.y.to_le_bytes());
+        data.extend_from_slice(&stats.sum.z.to_le_bytes());
+        data.extend_from_slice(&stats.sum_sq.to_le_bytes());
+        data.extend_from_slice(&stats.count.to_le_bytes());
+    }
+    for pixel in &renderer.film.pixels {
+        data.extend_from_slice(&pixel.sum.x.to_le_bytes());
+        data.extend_from_slice(&pixel.sum.y.to_le_bytes());
+        data.extend_from_slice(&pixel.sum.z.to_le_bytes());
+        data.extend_from_slice(&pixel.weight.to_le_bytes());
+    }
+    data
+}
+
+pub fn load(path: &str, camera: Camera) -> io::Result<Renderer> {
+    decode(&fs::read(path)?, camera)
+}
+
+// 文件里的数都不能相信：先检查尺寸和剩余的长度，再按尺寸分配内存
+fn decode(data: &[u8], camera: Camera) -> io::Result<Renderer> {
+    let mut reader = Reader { data, pos: 0 };
+    if reader.take(8)? != MAGIC {
+        return Err(invalid("not a raytracer checkpoint"));
+    }
+    let width = reader.u32()?;
+    let height = reader.u32()?;
+    let samples_per_pixel = reader.u32()?;
+    let max_depth = reader.u32()? as i32;
+    let seed = reader.u64()?;
+    let sampler = sampler_from_id(reader.take(1)?[0])?;
+    let error_threshold = reader.f64()?;
+    let min_samples = reader.u32()?;
+    let batch_samples = reader.u32()?;
+    let time_limit = reader.f64()?;
+    let target_noise = reader.f64()?;
+    let filter = PixelFilter {
+        kind: filter_from_id(reader.take(1)?[0])?,
+        radius: reader.f64()?,
+    };
+    if !filter.radius.is_finite() || filter.radius <= 0. {
+        return Err(invalid("invalid pixel filter radius in checkpoint"));
+    }
+    let crop = CropWindow {
+        x0: reader.u32()?,
+        y0: reader.u32()?,
+        x1: reader.u32()?,
+        y1: reader.u32()?,
+    };
+    if crop.x0 >= crop.x1 || crop.y0 >= crop.y1 || crop.x1 > width || crop.y1 > height {
+        return Err(invalid("invalid crop window in checkpoint"));
+    }
+    let elapsed = duration(reader.f64()?)?;
+    let pixel_count = width
+        .checked_mul(height)
+        .ok_or_else(|| invalid("image in checkpoint is too large"))? as usize;
+    if pixel_count.checked_mul(BYTES_PER_PIXEL) != Some(data.len() - reader.pos) {
+        return Err(invalid("checkpoint size does not match the image size"));
+    }
+    let settings = RenderSettings {
+        width,
+        height,
+        samples_per_pixel,
+        max_depth,
+        seed,
+        sampler,
+        error_threshold,
+        min_samples,
+        batch_samples,
+        time_limit: if time_limit < 0. {
+            None
+        } else {
+            Some(duration(time_limit)?)
+        },
+        target_noise,
+        filter,
+        crop,
+    };
+
+    let mut renderer = Renderer::new(settings, camera);
+    renderer.elapsed_before = elapsed;
+    for stats in renderer.stats.iter_mut() {
+        *stats = PixelStats {
+            sum: Vec3::new(reader.f64()?, reader.f64()?, reader.f64()?),
+            sum_sq: reader.f64()?,
+            count: reader.u32()?,
+        };
+    }
+    for pixel in renderer.film.pixels.iter_mut() {
+        *pixel = FilmPixel {
+            sum: Vec3::new(reader.f64()?, reader.f64()?, reader.f64()?),
+            weight: reader.f64()?,
+        };
+    }
+    if reader.pos != data.len() {
+        return Err(invalid("trailing data in checkpoint"));
+    }
+    Ok(renderer)
+}
+
+fn sampler_id(kind: SamplerKind) -> u8 {
+    match kind {
+        SamplerKind::Independent => 0,
+        SamplerKind::Stratified => 1,
+        SamplerKind::Halton => 2,
+        SamplerKind::Sobol => 3,
+    }
+}
+
+fn sampler_from_id(id: u8) -> io::Result<SamplerKind> {
+    match id {
+        0 => Ok(SamplerKind::Independent),
+        1 => Ok(SamplerKind::Stratified),
+        2 => Ok(SamplerKind::Halton),
+        3 => Ok(SamplerKind::Sobol),
+        _ => Err(invalid("unknown sampler in checkpoint")),
+    }
+}
+
+fn filter_id(kind: FilterKind) -> u8 {
+    match kind {
+        FilterKind::Box => 0,
+        FilterKind::Tent => 1,
+        FilterKind::Gaussian => 2,
+        FilterKind::Mitchell => 3,
+        FilterKind::Lanczos => 4,
+    }
+}
+
+fn filter_from_id(id: u8) -> io::Result<FilterKind> {
+    match id {
+        0 => Ok(FilterKind::Box),
+        1 => Ok(FilterKind::Tent),
+        2 => Ok(FilterKind::Gaussian),
+        3 => Ok(FilterKind::Mitchell),
+        4 => Ok(FilterKind::Lanczos),
+        _ => Err(invalid("unknown pixel filter in checkpoint")),
+    }
+}
+
+// NaN、无穷大和超出 Duration 范围的秒数会让 Duration::from_secs_f64 panic
+fn duration(seconds: f64) -> io::Result<Duration> {
+    if seconds.is_finite() && seconds >= 0. && seconds < u64::MAX as f64 {
+        Ok(Duration::from_secs_f64(seconds))
+    } else {
+        Err(invalid("invalid duration in checkpoint"))
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        if n > self.data.len() - self.pos {
+            return Err(invalid("checkpoint is truncated"));
+        }
+        let bytes = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn f64(&mut self) -> io::Result<f64> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::tests::{tiny_camera, tiny_settings};
+
+    // 头部各字段在文件里的位置
+    const SIZE: usize = 8;
+    const TIME_LIMIT: usize = 49;
+    const RADIUS: usize = 66;
+    const ELAPSED: usize = 90;
+
+    // 尺寸、时长或长度被改坏的检查点返回 InvalidData，不会 panic 或者先分配一大块内存
+    #[test]
+    fn hostile_checkpoints_are_rejected() {
+        let data = encode(&Renderer::new(tiny_settings(1), tiny_camera()));
+        assert!(decode(&data, tiny_camera()).is_ok());
+        let patch = |offset: usize, bytes: &[u8]| {
+            let mut data = data.clone();
+            data[offset..offset + bytes.len()].copy_from_slice(bytes);
+            data
+        };
+        let cases = vec![
+            patch(SIZE, &[0xff; 8]),
+            patch(SIZE, &[0, 0, 1, 0, 0, 0, 1, 0]), // 65536 x 65536 超出 u32
+            patch(SIZE, &[0, 4, 0, 0, 0, 4, 0, 0]), // 1024 x 1024，文件里没有这么多像素
+            patch(TIME_LIMIT, &f64::NAN.to_le_bytes()),
+            patch(TIME_LIMIT, &f64::INFINITY.to_le_bytes()),
+            patch(RADIUS, &f64::NAN.to_le_bytes()),
+            patch(ELAPSED, &1e300f64.to_le_bytes()),
+            patch(ELAPSED, &(-1f64).to_le_bytes()),
+            data[..data.len() - 1].to_vec(),
+            data[..ELAPSED].to_vec(),
+        ];
+        for (i, case) in cases.iter().enumerate() {
+            match decode(case, tiny_camera()) {
+                Err(e) => assert_eq!(e.kind(), ErrorKind::InvalidData, "case {}", i),
+                Ok(_) => panic!("case {} was accepted", i),
+            }
+        }
+    }
+}
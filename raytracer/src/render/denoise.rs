@@ -0,0 +1,246 @@
+use crate::{
+    basic::vec::Vec3,
+    render::{aov::AovBuffers, exr::ExrImage, framebuffer::Framebuffer},
+    utility,
+};
+
+// 引导降噪用到的特征，按图片的行序从上到下存放，缺少的特征不参与计算
+#[derive(Default)]
+pub struct Guides {
+    pub albedo: Option<Vec<Vec3>>,
+    pub normal: Option<Vec<Vec3>>,
+    pub depth: Option<Vec<f64>>,
+    pub variance: Option<Vec<f64>>, // 像素均值的方差（亮度）
+}
+
+impl Guides {
+    pub fn from_aovs(aovs: &AovBuffers, variance: Vec<f64>) -> Self {
+        Self {
+            albedo: Some(aovs.albedo.clone()),
+            normal: Some(aovs.normal.clone()),
+            depth: Some(aovs.depth.clone()),
+            variance: Some(variance),
+        }
+    }
+
+    // 从渲染输出的 EXR 图层里取特征：albedo.RGB、normal.XYZ、depth.Z、variance.Y
+    pub fn from_exr(image: &ExrImage) -> Self {
+        let channel = |name: &str| {
+            image
+                .channel(name)
+                .map(|c| c.data.iter().map(|v| *v as f64).collect::<Vec<f64>>())
+        };
+        let vec3 = |names: [&str; 3]| {
+            let x = channel(names[0])?;
+            let y = channel(names[1])?;
+            let z = channel(names[2])?;
+            Some(
+                (0..x.len())
+                    .map(|i| Vec3::new(x[i], y[i], z[i]))
+                    .collect::<Vec<Vec3>>(),
+            )
+        };
+        Self {
+            albedo: vec3(["albedo.R", "albedo.G", "albedo.B"]),
+            normal: vec3(["normal.X", "normal.Y", "normal.Z"]),
+            depth: channel("depth.Z"),
+            variance: channel("variance.Y"),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct DenoiseSettings {
+    pub radius: i32,       // 搜索窗口半径
+    pub patch_radius: i32, // 比较颜色时用的小块半径
+    pub sigma_albedo: f64,
+    pub sigma_normal: f64,
+    pub sigma_depth: f64, // 相对深度差
+    pub strength: f64,    // 颜色差异相对于噪声方差的容忍程度，越大越平滑
+}
+
+impl Default for DenoiseSettings {
+    fn default() -> Self {
+        Self {
+            radius: 7,
+            patch_radius: 1,
+            sigma_albedo: 0.1,
+            sigma_normal: 0.3,
+            sigma_depth: 0.05,
+            strength: 0.5,
+        }
+    }
+}
+
+// 联合非局部均值：颜色的差异按两边的方差归一化（Rousselle 等人的做法），
+// 再乘上反照率、法向量和深度的交叉双边权重，避免把边缘和纹理抹掉。
+// 有反照率时先除掉它，只对光照部分滤波，最后再乘回去。
+pub fn denoise(fb: &Framebuffer, guides: &Guides, settings: &DenoiseSettings) -> Framebuffer {
+    let width = fb.width as i32;
+    let height = fb.height as i32;
+    let n = (width * height) as usize;
+    let albedo_at = |i: usize| match &guides.albedo {
+        Some(albedo) => {
+            let a = albedo[i];
+            Vec3::new(a.x.max(0.01), a.y.max(0.01), a.z.max(0.01))
+        }
+        None => Vec3::ones(),
+    };
+
+    let index = |x: i32, y: i32| (y * width + x) as usize;
+
+    // 去掉反照率后的光照和它的方差
+    let mut irradiance = Vec::with_capacity(n);
+    for i in 0..n {
+        let a = albedo_at(i);
+        let c = fb.get(i as u32 % fb.width, i as u32 / fb.width);
+        irradiance.push(Vec3::new(c.x / a.x, c.y / a.y, c.z / a.z));
+    }
+    let variance: Vec<f64> = match &guides.variance {
+        Some(variance) => (0..n)
+            .map(|i| {
+                let l = utility::luminance(albedo_at(i));
+                variance[i] / (l * l)
+            })
+            .collect(),
+        // 没有渲染器给出的方差时，用 3x3 邻域的亮度方差粗略估计
+        None => (0..n)
+            .map(|i| {
+                let (x, y) = (i as i32 % width, i as i32 / width);
+                let mut sum = 0.;
+                let mut sum_sq = 0.;
+                let mut count = 0.;
+                for qy in (y - 1).max(0)..(y + 2).min(height) {
+                    for qx in (x - 1).max(0)..(x + 2).min(width) {
+                        let l = utility::luminance(irradiance[index(qx, qy)]);
+                        sum += l;
+                        sum_sq += l * l;
+                        count += 1.;
+                    }
+                }
+                let mean = sum / count;
+                // 方差 E[l²] - E[l]²，并不是 clippy 猜的笔误
+                #[allow(clippy::suspicious_operation_groupings)]
+                let variance = sum_sq / count - mean * mean;
+                variance.max(0.)
+            })
+            .collect(),
+    };
+
+    let sigma_spatial = settings.radius as f64 / 2.;
+    // 两个像素颜色差的平方，减去噪声的贡献后按方差归一化
+    let color_distance = |p: usize, q: usize| {
+        let d = irradiance[p] - irradiance[q];
+        let diff = utility::luminance(Vec3::new(d.x * d.x, d.y * d.y, d.z * d.z));
+        let var_p = variance[p];
+        let var_q = variance[q];
+        (diff - (var_p + var_p.min(var_q))) / (1e-4 + settings.strength * (var_p + var_q))
+    };
+
+    let mut result = fb.clone();
+    for y in 0..height {
+        for x in 0..width {
+            let p = index(x, y);
+            let mut sum = Vec3::zero();
+            let mut total = 0.;
+            for qy in (y - settings.radius).max(0)..(y + settings.radius + 1).min(height) {
+                for qx in (x - settings.radius).max(0)..(x + settings.radius + 1).min(width) {
+                    let q = index(qx, qy);
+                    let dx = (qx - x) as f64;
+                    let dy = (qy - y) as f64;
+                    let mut exponent = (dx * dx + dy * dy) / (2. * sigma_spatial * sigma_spatial);
+
+                    // 以 p、q 为中心的小块逐像素比较，取平均
+                    let mut patch = 0.;
+                    let mut count = 0.;
+                    for oy in -settings.patch_radius..settings.patch_radius + 1 {
+                        for ox in -settings.patch_radius..settings.patch_radius + 1 {
+                            let (px, py) = (x + ox, y + oy);
+                            let (sx, sy) = (qx + ox, qy + oy);
+                            if px < 0 || py < 0 || px >= width || py >= height {
+                                continue;
+                            }
+                            if sx < 0 || sy < 0 || sx >= width || sy >= height {
+                                continue;
+                            }
+                            patch += color_distance(index(px, py), index(sx, sy));
+                            count += 1.;
+                        }
+                    }
+                    exponent += (patch / count).max(0.);
+
+                    if let Some(albedo) = &guides.albedo {
+                        let d = albedo[p] - albedo[q];
+                        exponent += d.squared_length() / (2. * settings.sigma_albedo.powi(2));
+                    }
+                    if let Some(normal) = &guides.normal {
+                        let d = normal[p] - normal[q];
+                        exponent += d.squared_length() / (2. * settings.sigma_normal.powi(2));
+                    }
+                    if let Some(depth) = &guides.depth {
+                        let d = (depth[p] - depth[q]) / (settings.sigma_depth * depth[p].max(1e-3));
+                        exponent += d * d / 2.;
+                    }
+                    let w = (-exponent).exp();
+                    sum += irradiance[q] * w;
+                    total += w;
+                }
+            }
+            // 中心像素自身的权重至少为 1，total 不会是 0
+            result.set(x as u32, y as u32, (sum / total).elemul(albedo_at(p)));
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utility::RandGen;
+    use rand::{Rng, SeedableRng};
+
+    fn luminance_variance(fb: &Framebuffer) -> f64 {
+        let values: Vec<f64> = fb
+            .pixels
+            .iter()
+            .map(|p| utility::luminance(Vec3::new(p[0] as f64, p[1] as f64, p[2] as f64)))
+            .collect();
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        values.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / values.len() as f64
+    }
+
+    #[test]
+    fn constant_image_is_unchanged() {
+        let mut fb = Framebuffer::new(16, 12);
+        for y in 0..12 {
+            for x in 0..16 {
+                fb.set(x, y, Vec3::new(0.2, 0.5, 0.8));
+            }
+        }
+        let result = denoise(&fb, &Guides::default(), &DenoiseSettings::default());
+        for (a, b) in fb.pixels.iter().zip(result.pixels.iter()) {
+            for c in 0..4 {
+                assert!((a[c] - b[c]).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn noisy_flat_patch_gets_smoother() {
+        let mut rng = RandGen::seed_from_u64(5);
+        let mut fb = Framebuffer::new(24, 24);
+        for y in 0..24 {
+            for x in 0..24 {
+                let noise = rng.gen_range(-0.2..0.2);
+                fb.set(x, y, Vec3::ones() * (0.5 + noise));
+            }
+        }
+        let before = luminance_variance(&fb);
+        let after = luminance_variance(&denoise(
+            &fb,
+            &Guides::default(),
+            &DenoiseSettings::default(),
+        ));
+        assert!(after < 0.5 * before, "variance {} -> {}", before, after);
+    }
+}
@@ -0,0 +1,418 @@
+use std::{
+    convert::TryFrom,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+};
+
+use crate::render::framebuffer::Framebuffer;
+
+// 一个 32 位浮点通道，名字里的点号表示层，例如 "albedo.R"
+pub struct ExrChannel {
+    pub name: String,
+    pub data: Vec<f32>, // 按行从上到下
+}
+
+impl ExrChannel {
+    pub fn new(name: &str, data: Vec<f32>) -> Self {
+        Self {
+            name: name.to_string(),
+            data,
+        }
+    }
+}
+
+pub fn framebuffer_channels(fb: &Framebuffer) -> Vec<ExrChannel> {
+    vec![
+        ExrChannel::new("R", fb.channel(0)),
+        ExrChannel::new("G", fb.channel(1)),
+        ExrChannel::new("B", fb.channel(2)),
+        ExrChannel::new("A", fb.channel(3)),
+    ]
+}
+
+// 写出不压缩的单部分扫描线 OpenEXR 文件，每个扫描线块一行
+pub fn write_exr(path: &str, width: u32, height: u32, channels: &[ExrChannel]) -> io::Result<()> {
+    let pixel_count = (width * height) as usize;
+    if channels.iter().any(|c| c.data.len() != pixel_count) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "channel size does not match the image",
+        ));
+    }
+    // 通道在文件里必须按名字排序
+    let mut order: Vec<&ExrChannel> = channels.iter().collect();
+    order.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&20000630u32.to_le_bytes()); // magic
+    header.extend_from_slice(&2u32.to_le_bytes()); // 版本 2，单部分扫描线
+
+    let mut chlist = Vec::new();
+    for channel in &order {
+        chlist.extend_from_slice(channel.name.as_bytes());
+        chlist.push(0);
+        chlist.extend_from_slice(&2i32.to_le_bytes()); // FLOAT
+        chlist.extend_from_slice(&[0, 0, 0, 0]); // pLinear 和保留字节
+        chlist.extend_from_slice(&1i32.to_le_bytes()); // xSampling
+        chlist.extend_from_slice(&1i32.to_le_bytes()); // ySampling
+    }
+    chlist.push(0);
+    attribute(&mut header, "channels", "chlist", &chlist);
+    attribute(&mut header, "compression", "compression", &[0]);
+    let mut window = Vec::new();
+    for v in &[0, 0, width as i32 - 1, height as i32 - 1] {
+        window.extend_from_slice(&v.to_le_bytes());
+    }
+    attribute(&mut header, "dataWindow", "box2i", &window);
+    attribute(&mut header, "displayWindow", "box2i", &window);
+    attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1f32.to_le_bytes(),
+    );
+    let mut center = Vec::new();
+    center.extend_from_slice(&0f32.to_le_bytes());
+    center.extend_from_slice(&0f32.to_le_bytes());
+    attribute(&mut header, "screenWindowCenter", "v2f", &center);
+    attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1f32.to_le_bytes(),
+    );
+    header.push(0);
+
+    let line_size = width as usize * 4 * order.len();
+    let mut file = BufWriter::new(File::create(path)?);
+    file.write_all(&header)?;
+    // 扫描线偏移表
+    let first = header.len() + height as usize * 8;
+    for y in 0..height as usize {
+        let offset = (first + y * (line_size + 8)) as u64;
+        file.write_all(&offset.to_le_bytes())?;
+    }
+    for y in 0..height as usize {
+        file.write_all(&(y as i32).to_le_bytes())?;
+        file.write_all(&(line_size as i32).to_le_bytes())?;
+        for channel in &order {
+            let row = &channel.data[y * width as usize..(y + 1) * width as usize];
+            for v in row {
+                file.write_all(&v.to_le_bytes())?;
+            }
+        }
+    }
+    file.flush()
+}
+
+fn attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+pub struct ExrImage {
+    pub width: u32,
+    pub height: u32,
+    pub channels: Vec<ExrChannel>,
+}
+
+impl ExrImage {
+    pub fn channel(&self, name: &str) -> Option<&ExrChannel> {
+        self.channels.iter().find(|c| c.name == name)
+    }
+
+    // 把一块 width 宽、左上角在 (x0, y0) 的通道数据贴到同名通道上，没有的通道先补 0
+    pub fn paste(
+        &mut self,
+        channels: &[ExrChannel],
+        x0: u32,
+        y0: u32,
+        width: u32,
+    ) -> io::Result<()> {
+        let pixel_count = (self.width * self.height) as usize;
+        for channel in channels {
+            let height = channel.data.len() as u32 / width.max(1);
+            if x0 + width > self.width || y0 + height > self.height {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "the {}x{} image is too small to paste {}x{} at ({}, {})",
+                        self.width, self.height, width, height, x0, y0
+                    ),
+                ));
+            }
+            let index = match self.channels.iter().position(|c| c.name == channel.name) {
+                Some(index) => index,
+                None => {
+                    self.channels
+                        .push(ExrChannel::new(&channel.name, vec![0.; pixel_count]));
+                    self.channels.len() - 1
+                }
+            };
+            let target = &mut self.channels[index].data;
+            for (i, v) in channel.data.iter().enumerate() {
+                let x = x0 + i as u32 % width;
+                let y = y0 + i as u32 / width;
+                target[(y * self.width + x) as usize] = *v;
+            }
+        }
+        Ok(())
+    }
+
+    // 取出 R、G、B（和可选的 A）组成帧缓冲，layer 为空时取不带层名的通道
+    pub fn framebuffer(&self, layer: &str) -> io::Result<Framebuffer> {
+        let name = |c: &str| {
+            if layer.is_empty() {
+                c.to_string()
+            } else {
+                format!("{}.{}", layer, c)
+            }
+        };
+        let mut fb = Framebuffer::new(self.width, self.height);
+        for (i, c) in ["R", "G", "B", "A"].iter().enumerate() {
+            match self.channel(&name(c)) {
+                Some(channel) => {
+                    for (p, v) in fb.pixels.iter_mut().zip(channel.data.iter()) {
+                        p[i] = *v;
+                    }
+                }
+                None if i == 3 => {}
+                None => return Err(invalid(&format!("missing channel \"{}\"", name(c)))),
+            }
+        }
+        Ok(fb)
+    }
+}
+
+// 读取不压缩的单部分扫描线 OpenEXR，支持 HALF、FLOAT 和 UINT 通道
+pub fn read_exr(path: &str) -> io::Result<ExrImage> {
+    decode_exr(&fs::read(path)?)
+}
+
+fn decode_exr(data: &[u8]) -> io::Result<ExrImage> {
+    let mut pos = 0;
+    if read_u32(data, &mut pos)? != 20000630 {
+        return Err(invalid("not an OpenEXR file"));
+    }
+    let version = read_u32(data, &mut pos)?;
+    if version & 0xff != 2 || version & 0x1e00 != 0 {
+        return Err(invalid(
+            "only single-part scanline OpenEXR files are supported",
+        ));
+    }
+
+    let mut channel_types: Vec<(String, i32)> = Vec::new();
+    let mut compression = 0;
+    let mut window = None;
+    loop {
+        let name = read_string(data, &mut pos)?;
+        if name.is_empty() {
+            break;
+        }
+        let _kind = read_string(data, &mut pos)?;
+        let size = read_u32(data, &mut pos)? as usize;
+        let value = take(data, &mut pos, size)?;
+        match name.as_str() {
+            "channels" => {
+                let mut p = 0;
+                loop {
+                    let channel = read_string(value, &mut p)?;
+                    if channel.is_empty() {
+                        break;
+                    }
+                    let pixel_type = read_u32(value, &mut p)? as i32;
+                    take(value, &mut p, 4)?;
+                    let x_sampling = read_u32(value, &mut p)?;
+                    let y_sampling = read_u32(value, &mut p)?;
+                    if x_sampling != 1 || y_sampling != 1 {
+                        return Err(invalid("subsampled channels are not supported"));
+                    }
+                    channel_types.push((channel, pixel_type));
+                }
+            }
+            "compression" => compression = *value.first().unwrap_or(&0),
+            "dataWindow" => {
+                let mut p = 0;
+                let mut b = [0i32; 4];
+                for v in b.iter_mut() {
+                    *v = read_u32(value, &mut p)? as i32;
+                }
+                window = Some(b);
+            }
+            _ => {}
+        }
+    }
+    if compression != 0 {
+        return Err(invalid("compressed OpenEXR files are not supported"));
+    }
+    let window = window.ok_or_else(|| invalid("missing dataWindow"))?;
+    // 尺寸和偏移都来自文件，不能相信，算术一律检查溢出
+    let size = |min: i32, max: i32| u32::try_from((max as i64 - min as i64 + 1).max(0)).ok();
+    let too_large = || invalid("dataWindow is too large");
+    let width = size(window[0], window[2]).ok_or_else(too_large)?;
+    let height = size(window[1], window[3]).ok_or_else(too_large)?;
+    let pixel_count = width.checked_mul(height).ok_or_else(too_large)? as usize;
+    // 每个通道每个像素至少占 2 字节，文件装不下时不要先分配内存
+    if pixel_count
+        .checked_mul(2 * channel_types.len())
+        .map_or(true, |n| n > data.len())
+    {
+        return Err(invalid("OpenEXR file is truncated"));
+    }
+
+    let mut offsets = Vec::new();
+    for _ in 0..height {
+        let low = read_u32(data, &mut pos)? as u64;
+        let high = read_u32(data, &mut pos)? as u64;
+        let offset =
+            usize::try_from(high << 32 | low).map_err(|_| invalid("OpenEXR file is truncated"))?;
+        offsets.push(offset);
+    }
+    let mut channels: Vec<ExrChannel> = channel_types
+        .iter()
+        .map(|(name, _)| ExrChannel::new(name, vec![0.; pixel_count]))
+        .collect();
+    for offset in offsets {
+        let mut p = offset;
+        let y = read_u32(data, &mut p)? as i32 as i64 - window[1] as i64;
+        let _size = read_u32(data, &mut p)?;
+        if y < 0 || y >= height as i64 {
+            return Err(invalid("scanline outside the data window"));
+        }
+        let row = y as usize * width as usize;
+        for ((_, pixel_type), channel) in channel_types.iter().zip(channels.iter_mut()) {
+            for x in 0..width as usize {
+                channel.data[row + x] = match pixel_type {
+                    0 => read_u32(data, &mut p)? as f32,
+                    1 => {
+                        let bytes = take(data, &mut p, 2)?;
+                        half_to_f32(u16::from_le_bytes([bytes[0], bytes[1]]))
+                    }
+                    2 => f32::from_bits(read_u32(data, &mut p)?),
+                    _ => return Err(invalid("unknown pixel type")),
+                };
+            }
+        }
+    }
+    Ok(ExrImage {
+        width,
+        height,
+        channels,
+    })
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn take<'a>(data: &'a [u8], pos: &mut usize, n: usize) -> io::Result<&'a [u8]> {
+    let end = match pos.checked_add(n) {
+        Some(end) if end <= data.len() => end,
+        _ => return Err(invalid("OpenEXR file is truncated")),
+    };
+    let bytes = &data[*pos..end];
+    *pos = end;
+    Ok(bytes)
+}
+
+fn read_u32(data: &[u8], pos: &mut usize) -> io::Result<u32> {
+    let b = take(data, pos, 4)?;
+    Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn read_string(data: &[u8], pos: &mut usize) -> io::Result<String> {
+    let start = *pos;
+    while *pos < data.len() && data[*pos] != 0 {
+        *pos += 1;
+    }
+    if *pos >= data.len() {
+        return Err(invalid("OpenEXR file is truncated"));
+    }
+    let s = String::from_utf8_lossy(&data[start..*pos]).into_owned();
+    *pos += 1;
+    Ok(s)
+}
+
+// IEEE 半精度转单精度
+fn half_to_f32(h: u16) -> f32 {
+    let sign = if h & 0x8000 != 0 { -1. } else { 1. };
+    let exponent = ((h >> 10) & 0x1f) as i32;
+    let mantissa = (h & 0x3ff) as f32;
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        31 if mantissa == 0. => sign * f32::INFINITY,
+        31 => f32::NAN,
+        _ => sign * (1. + mantissa / 1024.) * 2f32.powi(exponent - 15),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_image() -> Vec<ExrChannel> {
+        vec![
+            ExrChannel::new("R", vec![0., 0.5, 1., 2.5, -1., 1e6]),
+            ExrChannel::new("G", vec![0.25; 6]),
+            ExrChannel::new("albedo.B", vec![1., 2., 3., 4., 5., 6.]),
+        ]
+    }
+
+    #[test]
+    fn write_then_read_round_trips() {
+        let path = std::env::temp_dir().join(format!("raytracer-exr-{}.exr", std::process::id()));
+        let path = path.to_str().unwrap();
+        let channels = sample_image();
+        write_exr(path, 3, 2, &channels).unwrap();
+        let image = read_exr(path);
+        fs::remove_file(path).unwrap();
+        let image = image.unwrap();
+        assert_eq!((image.width, image.height), (3, 2));
+        assert_eq!(image.channels.len(), channels.len());
+        for channel in &channels {
+            assert_eq!(image.channel(&channel.name).unwrap().data, channel.data);
+        }
+    }
+
+    // 只有头部，dataWindow 由参数给出，之后接上 offsets
+    fn header_with_window(window: [i32; 4], offsets: &[u64]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&20000630u32.to_le_bytes());
+        data.extend_from_slice(&2u32.to_le_bytes());
+        let mut chlist = b"R\0".to_vec();
+        chlist.extend_from_slice(&2i32.to_le_bytes());
+        chlist.extend_from_slice(&[0, 0, 0, 0]);
+        chlist.extend_from_slice(&1i32.to_le_bytes());
+        chlist.extend_from_slice(&1i32.to_le_bytes());
+        chlist.push(0);
+        attribute(&mut data, "channels", "chlist", &chlist);
+        attribute(&mut data, "compression", "compression", &[0]);
+        let mut bounds = Vec::new();
+        for v in &window {
+            bounds.extend_from_slice(&v.to_le_bytes());
+        }
+        attribute(&mut data, "dataWindow", "box2i", &bounds);
+        data.push(0);
+        for offset in offsets {
+            data.extend_from_slice(&offset.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn hostile_headers_are_rejected() {
+        let huge_window = header_with_window([i32::MIN, i32::MIN, i32::MAX, i32::MAX], &[]);
+        assert!(decode_exr(&huge_window).is_err());
+        let wide_window = header_with_window([0, 0, 1 << 20, 1 << 20], &[]);
+        assert!(decode_exr(&wide_window).is_err());
+        let huge_offset = header_with_window([0, 0, 0, 0], &[u64::MAX - 2]);
+        assert!(decode_exr(&huge_offset).is_err());
+        let truncated = header_with_window([0, 0, 0, 0], &[]);
+        assert!(decode_exr(&truncated).is_err());
+    }
+}
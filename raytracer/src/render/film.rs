@@ -0,0 +1,310 @@
+use crate::basic::vec::Vec3;
+
+// 像素重建滤波器：每个样本按它到像素中心的距离加权，分摊到半径内的所有像素
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FilterKind {
+    Box, // 半径 0.5 时就是像素内直接平均
+    Tent,
+    Gaussian,
+    Mitchell, // B = C = 1/3
+    Lanczos,  // 半径即 sinc 的瓣数
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PixelFilter {
+    pub kind: FilterKind,
+    pub radius: f64, // 单位为像素
+}
+
+impl Default for PixelFilter {
+    fn default() -> Self {
+        PixelFilter::new(FilterKind::Box)
+    }
+}
+
+impl PixelFilter {
+    // 使用各滤波器常用的半径
+    pub fn new(kind: FilterKind) -> Self {
+        let radius = match kind {
+            FilterKind::Box => 0.5,
+            FilterKind::Tent => 1.,
+            FilterKind::Gaussian => 1.5,
+            FilterKind::Mitchell => 2.,
+            FilterKind::Lanczos => 3.,
+        };
+        Self { kind, radius }
+    }
+
+    // "mitchell" 或 "mitchell:1.5"，冒号后是半径
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        let mut parts = name.splitn(2, ':');
+        let kind = match parts.next()? {
+            "box" => FilterKind::Box,
+            "tent" | "triangle" => FilterKind::Tent,
+            "gaussian" => FilterKind::Gaussian,
+            "mitchell" => FilterKind::Mitchell,
+            "lanczos" => FilterKind::Lanczos,
+            _ => return None,
+        };
+        let mut filter = PixelFilter::new(kind);
+        if let Some(radius) = parts.next() {
+            filter.radius = radius.parse().ok()?;
+            if !filter.radius.is_finite() || filter.radius <= 0. {
+                return None;
+            }
+        }
+        Some(filter)
+    }
+
+    // 可分离的二维滤波器在一个方向上的权重，d 是样本位置减去像素中心
+    fn weight_1d(&self, d: f64) -> f64 {
+        let r = self.radius;
+        // 左闭右开，落在像素边界上的样本只算给一个像素
+        if d < -r || d >= r {
+            return 0.;
+        }
+        match self.kind {
+            FilterKind::Box => 1.,
+            FilterKind::Tent => r - d.abs(),
+            // 减去边界处的值，让权重在半径处连续降到 0
+            FilterKind::Gaussian => {
+                let sigma = r / 3.;
+                let g = |x: f64| (-x * x / (2. * sigma * sigma)).exp();
+                (g(d) - g(r)).max(0.)
+            }
+            FilterKind::Mitchell => mitchell(2. * d / r),
+            FilterKind::Lanczos => sinc(d) * sinc(d / r),
+        }
+    }
+}
+
+// Mitchell-Netravali 三次曲线，x 在 [-2, 2] 内
+fn mitchell(x: f64) -> f64 {
+    const B: f64 = 1. / 3.;
+    const C: f64 = 1. / 3.;
+    let x = x.abs();
+    let value = if x < 1. {
+        (12. - 9. * B - 6. * C) * x * x * x + (-18. + 12. * B + 6. * C) * x * x + (6. - 2. * B)
+    } else if x < 2. {
+        (-B - 6. * C) * x * x * x
+            + (6. * B + 30. * C) * x * x
+            + (-12. * B - 48. * C) * x
+            + (8. * B + 24. * C)
+    } else {
+        0.
+    };
+    value / 6.
+}
+
+fn sinc(x: f64) -> f64 {
+    let x = x.abs();
+    if x < 1e-5 {
+        return 1.;
+    }
+    let pi_x = std::f64::consts::PI * x;
+    pi_x.sin() / pi_x
+}
+
+// 一个像素收到的加权样本和与权重和
+#[derive(Clone, Copy, Default)]
+pub struct FilmPixel {
+    pub sum: Vec3,
+    pub weight: f64,
+}
+
+// 整张图的累加结果，按 y * width + x 存放，y 自下而上（与 Renderer::stats 相同）
+pub struct Film {
+    pub width: u32,
+    pub height: u32,
+    pub filter: PixelFilter,
+    pub pixels: Vec<FilmPixel>,
+}
+
+impl Film {
+    pub fn new(width: u32, height: u32, filter: PixelFilter) -> Self {
+        Self {
+            width,
+            height,
+            filter,
+            pixels: vec![FilmPixel::default(); (width * height) as usize],
+        }
+    }
+
+    // 一个像素的样本最远能影响到几个像素以外
+    pub fn border(&self) -> u32 {
+        (self.filter.radius - 0.5).ceil().max(0.) as u32
+    }
+
+    // 为像素 [x0, x1) x [y0, y1) 的样本准备一块 tile，范围向外扩展滤波器半径
+    // 各 tile 可以分别采样（例如在不同线程里），再用 merge 合并回来
+    pub fn tile(&self, x0: u32, y0: u32, x1: u32, y1: u32) -> FilmTile {
+        let border = self.border();
+        let x0 = x0.saturating_sub(border);
+        let y0 = y0.saturating_sub(border);
+        let x1 = x1.saturating_add(border).min(self.width);
+        let y1 = y1.saturating_add(border).min(self.height);
+        FilmTile {
+            x0,
+            y0,
+            x1,
+            y1,
+            filter: self.filter,
+            pixels: vec![FilmPixel::default(); ((x1 - x0) * (y1 - y0)) as usize],
+        }
+    }
+
+    pub fn merge(&mut self, tile: FilmTile) {
+        let tile_width = tile.x1 - tile.x0;
+        for y in tile.y0..tile.y1 {
+            for x in tile.x0..tile.x1 {
+                let from = tile.pixels[((y - tile.y0) * tile_width + x - tile.x0) as usize];
+                let to = &mut self.pixels[(y * self.width + x) as usize];
+                to.sum += from.sum;
+                to.weight += from.weight;
+            }
+        }
+    }
+
+    // 重建出的像素值；Mitchell 和 Lanczos 有负的旁瓣，结果截到非负
+    pub fn resolve(&self, x: u32, y: u32) -> Vec3 {
+        let pixel = self.pixels[(y * self.width + x) as usize];
+        if pixel.weight <= 0. {
+            return Vec3::zero();
+        }
+        let c = pixel.sum / pixel.weight;
+        Vec3::new(c.x.max(0.), c.y.max(0.), c.z.max(0.))
+    }
+}
+
+pub struct FilmTile {
+    pub x0: u32,
+    pub y0: u32,
+    pub x1: u32,
+    pub y1: u32,
+    pub filter: PixelFilter,
+    pub pixels: Vec<FilmPixel>,
+}
+
+impl FilmTile {
+    // (x, y) 是样本在胶片上的连续坐标，像素 (i, j) 的中心在 (i + 0.5, j + 0.5)
+    pub fn add_sample(&mut self, x: f64, y: f64, color: Vec3) {
+        let r = self.filter.radius;
+        let first_x = ((x - 0.5 - r).floor().max(self.x0 as f64)) as u32;
+        let first_y = ((y - 0.5 - r).floor().max(self.y0 as f64)) as u32;
+        let last_x = ((x - 0.5 + r).ceil().min(self.x1 as f64 - 1.)) as u32;
+        let last_y = ((y - 0.5 + r).ceil().min(self.y1 as f64 - 1.)) as u32;
+        let tile_width = self.x1 - self.x0;
+        for py in first_y..=last_y {
+            let wy = self.filter.weight_1d(y - (py as f64 + 0.5));
+            if wy == 0. {
+                continue;
+            }
+            for px in first_x..=last_x {
+                let w = self.filter.weight_1d(x - (px as f64 + 0.5)) * wy;
+                if w == 0. {
+                    continue;
+                }
+                let pixel = &mut self.pixels[((py - self.y0) * tile_width + px - self.x0) as usize];
+                pixel.sum += color * w;
+                pixel.weight += w;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KINDS: [FilterKind; 5] = [
+        FilterKind::Box,
+        FilterKind::Tent,
+        FilterKind::Gaussian,
+        FilterKind::Mitchell,
+        FilterKind::Lanczos,
+    ];
+
+    #[test]
+    fn box_filter_gives_the_pixel_mean() {
+        let filter = PixelFilter::from_name("box:0.5").unwrap();
+        let mut film = Film::new(3, 2, filter);
+        let mut tile = film.tile(0, 0, 3, 2);
+        let samples = [
+            (1.1, 0.2, Vec3::new(1., 0., 0.)),
+            (1.9, 0.9, Vec3::new(0., 2., 0.)),
+            (1.5, 0.5, Vec3::new(0., 0., 3.)),
+            (0.5, 1.5, Vec3::new(4., 4., 4.)),
+        ];
+        for &(x, y, color) in &samples {
+            tile.add_sample(x, y, color);
+        }
+        film.merge(tile);
+        let mean = film.resolve(1, 0);
+        assert!((mean - Vec3::new(1., 2., 3.) / 3.).length() < 1e-12);
+        assert!((film.resolve(0, 1) - Vec3::new(4., 4., 4.)).length() < 1e-12);
+        assert_eq!(film.pixels[2].weight, 0.);
+    }
+
+    #[test]
+    fn weights_vanish_outside_the_radius() {
+        for &kind in &KINDS {
+            let filter = PixelFilter::new(kind);
+            let r = filter.radius;
+            assert!(filter.weight_1d(0.) > 0., "{:?}", kind);
+            for &d in &[r, r + 0.01, r + 1., -r - 0.01, -r - 1.] {
+                assert_eq!(filter.weight_1d(d), 0., "{:?} at {}", kind, d);
+            }
+        }
+    }
+
+    #[test]
+    fn samples_near_a_boundary_reach_both_neighbours() {
+        for &kind in &[FilterKind::Tent, FilterKind::Gaussian] {
+            let mut film = Film::new(4, 1, PixelFilter::new(kind));
+            let mut tile = film.tile(0, 0, 4, 1);
+            // 落在像素 1 和 2 的交界附近
+            tile.add_sample(2.05, 0.5, Vec3::ones());
+            film.merge(tile);
+            assert!(film.pixels[1].weight > 0., "{:?}", kind);
+            assert!(film.pixels[2].weight > 0., "{:?}", kind);
+            assert!(film.pixels[2].weight > film.pixels[1].weight, "{:?}", kind);
+        }
+    }
+
+    // 分成行 tile 采样再合并，与整张图一次累加的结果相同
+    #[test]
+    fn merged_tiles_match_direct_accumulation() {
+        let filter = PixelFilter::new(FilterKind::Mitchell);
+        let (width, height) = (9, 7);
+        let samples: Vec<(f64, f64, Vec3)> = (0..width * height * 4)
+            .map(|i| {
+                let (x, y) = ((i / 4) % width, (i / 4) / width);
+                let jitter = (i % 4) as f64 * 0.23 + 0.1;
+                let color = Vec3::new(i as f64 * 0.01, 1., (i % 7) as f64);
+                (x as f64 + jitter, y as f64 + 1. - jitter, color)
+            })
+            .collect();
+
+        let mut direct = Film::new(width, height, filter);
+        let mut whole = direct.tile(0, 0, width, height);
+        for &(x, y, color) in &samples {
+            whole.add_sample(x, y, color);
+        }
+        direct.merge(whole);
+
+        let mut tiled = Film::new(width, height, filter);
+        for row in 0..height {
+            let mut tile = tiled.tile(0, row, width, row + 1);
+            for &(x, y, color) in samples.iter().filter(|s| s.1.floor() as u32 == row) {
+                tile.add_sample(x, y, color);
+            }
+            tiled.merge(tile);
+        }
+
+        for (a, b) in direct.pixels.iter().zip(tiled.pixels.iter()) {
+            assert!((a.sum - b.sum).length() < 1e-9);
+            assert!((a.weight - b.weight).abs() < 1e-9);
+        }
+    }
+}
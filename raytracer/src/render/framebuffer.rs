@@ -0,0 +1,45 @@
+use image::{ImageBuffer, RgbImage};
+
+use crate::{basic::vec::Vec3, render::tonemap::DisplayTransform};
+
+// 线性颜色的浮点帧缓冲，按图片的行序从上到下存放，不做任何色调映射
+#[derive(Clone)]
+pub struct Framebuffer {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[f32; 4]>, // RGBA
+}
+
+impl Framebuffer {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![[0., 0., 0., 1.]; (width * height) as usize],
+        }
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Vec3 {
+        let p = self.pixels[(y * self.width + x) as usize];
+        Vec3::new(p[0] as f64, p[1] as f64, p[2] as f64)
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, color: Vec3) {
+        let p = &mut self.pixels[(y * self.width + x) as usize];
+        p[0] = color.x as f32;
+        p[1] = color.y as f32;
+        p[2] = color.z as f32;
+    }
+
+    // 取出一个通道，c 为 0..4 对应 RGBA
+    pub fn channel(&self, c: usize) -> Vec<f32> {
+        self.pixels.iter().map(|p| p[c]).collect()
+    }
+
+    // 经过显示变换转为 8 位 sRGB 图片
+    pub fn to_rgb8(&self, display: &DisplayTransform) -> RgbImage {
+        ImageBuffer::from_fn(self.width, self.height, |x, y| {
+            image::Rgb(display.rgb8(self.get(x, y)))
+        })
+    }
+}
@@ -0,0 +1,595 @@
+pub mod aov;
+pub mod checkpoint;
+pub mod denoise;
+pub mod exr;
+pub mod film;
+pub mod framebuffer;
+pub mod output;
+pub mod tonemap;
+
+use image::{ImageBuffer, RgbImage};
+use indicatif::ProgressBar;
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    basic::{camera::Camera, ray::Ray, vec::Vec3},
+    hittable::Hittable,
+    render::{
+        film::{Film, FilmTile, PixelFilter},
+        framebuffer::Framebuffer,
+    },
+    sampler::{Sampler, SamplerKind},
+    utility,
+};
+
+#[derive(Clone, Copy)]
+pub struct RenderSettings {
+    pub width: u32,
+    pub height: u32,
+    pub samples_per_pixel: u32, // 每个像素最多的样本数
+    pub max_depth: i32,
+    pub seed: u64,
+    pub sampler: SamplerKind,
+    // 自适应采样：误差阈值为 0 时关闭，每个像素都采满 samples_per_pixel
+    pub error_threshold: f64,
+    pub min_samples: u32,
+    pub batch_samples: u32, // 之后每一遍给未收敛的像素追加的样本数
+    // 渐进式渲染的停止条件，为 None 或 0 时不启用
+    pub time_limit: Option<Duration>,
+    pub target_noise: f64, // 全图平均相对误差降到这个值以下就停止
+    pub filter: PixelFilter,
+    pub crop: CropWindow, // 只渲染这部分像素
+}
+
+impl RenderSettings {
+    pub fn adaptive(&self) -> bool {
+        self.error_threshold > 0.
+    }
+}
+
+// 图片上的一个矩形区域，像素坐标，原点在左上角，不含右边和下边
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CropWindow {
+    pub x0: u32,
+    pub y0: u32,
+    pub x1: u32,
+    pub y1: u32,
+}
+
+impl CropWindow {
+    pub fn full(width: u32, height: u32) -> Self {
+        Self {
+            x0: 0,
+            y0: 0,
+            x1: width,
+            y1: height,
+        }
+    }
+
+    // 截到图片范围内，空的区域返回 None
+    pub fn from_pixels(bounds: [f64; 4], width: u32, height: u32) -> Option<Self> {
+        let clamp = |v: f64, max: u32| utility::clamp(v.round(), 0., max as f64) as u32;
+        let window = Self {
+            x0: clamp(bounds[0], width),
+            y0: clamp(bounds[1], height),
+            x1: clamp(bounds[2], width),
+            y1: clamp(bounds[3], height),
+        };
+        if window.x0 < window.x1 && window.y0 < window.y1 {
+            Some(window)
+        } else {
+            None
+        }
+    }
+
+    // [0, 1] 内的坐标，向外取整到整像素
+    pub fn from_normalized(bounds: [f64; 4], width: u32, height: u32) -> Option<Self> {
+        let (w, h) = (width as f64, height as f64);
+        Self::from_pixels(
+            [
+                (bounds[0] * w).floor(),
+                (bounds[1] * h).floor(),
+                (bounds[2] * w).ceil(),
+                (bounds[3] * h).ceil(),
+            ],
+            width,
+            height,
+        )
+    }
+
+    pub fn width(&self) -> u32 {
+        self.x1 - self.x0
+    }
+
+    pub fn height(&self) -> u32 {
+        self.y1 - self.y0
+    }
+
+    // 四周各扩展 border 个像素，不超出图片
+    pub fn expand(&self, border: u32, width: u32, height: u32) -> Self {
+        Self {
+            x0: self.x0.saturating_sub(border),
+            y0: self.y0.saturating_sub(border),
+            x1: self.x1.saturating_add(border).min(width),
+            y1: self.y1.saturating_add(border).min(height),
+        }
+    }
+}
+
+// 一个像素的样本统计，方差用亮度估计
+#[derive(Clone, Copy, Default)]
+pub struct PixelStats {
+    pub sum: Vec3,
+    pub sum_sq: f64,
+    pub count: u32,
+}
+
+impl PixelStats {
+    pub fn add(&mut self, color: Vec3) {
+        let l = utility::luminance(color);
+        self.sum += color;
+        self.sum_sq += l * l;
+        self.count += 1;
+    }
+
+    pub fn mean(&self) -> Vec3 {
+        if self.count == 0 {
+            return Vec3::zero();
+        }
+        self.sum / self.count as f64
+    }
+
+    // 样本方差（无偏）
+    pub fn variance(&self) -> f64 {
+        if self.count < 2 {
+            return f64::INFINITY;
+        }
+        let n = self.count as f64;
+        let mean = utility::luminance(self.sum) / n;
+        utility::fmax(0., (self.sum_sq / n - mean * mean) * n / (n - 1.))
+    }
+
+    // 均值的相对标准误差，暗处加一个小量避免除零
+    pub fn relative_error(&self) -> f64 {
+        let mean = utility::luminance(self.mean());
+        (self.variance() / self.count as f64).sqrt() / (mean + 0.01)
+    }
+}
+
+// 按 Ctrl-C 后置位，渲染在当前行结束后停下
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+// ctrlc 只允许设置一次处理函数，在 main 里调用一次
+pub fn install_interrupt_handler() {
+    let result = ctrlc::set_handler(|| INTERRUPTED.store(true, Ordering::SeqCst));
+    if result.is_err() {
+        println!("Failed to install the Ctrl-C handler.");
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum StopReason {
+    Converged, // 所有像素收敛或采满
+    TimeLimit,
+    TargetNoise,
+    Interrupted,
+}
+
+pub struct Renderer {
+    pub settings: RenderSettings,
+    pub camera: Camera,
+    pub stats: Vec<PixelStats>, // 按 y * width + x 存放，y 自下而上；自适应采样和方差只看像素自己的样本
+    pub film: Film,             // 经过重建滤波的图像
+    pub elapsed_before: Duration, // 从检查点恢复时之前已经渲染的时间
+    run_start: Instant,
+    sampler: Box<dyn Sampler>,
+}
+
+impl Renderer {
+    pub fn new(settings: RenderSettings, camera: Camera) -> Self {
+        Self {
+            settings,
+            camera,
+            stats: vec![PixelStats::default(); (settings.width * settings.height) as usize],
+            film: Film::new(settings.width, settings.height, settings.filter),
+            elapsed_before: Duration::from_secs(0),
+            run_start: Instant::now(),
+            sampler: settings
+                .sampler
+                .create(settings.seed, settings.samples_per_pixel),
+        }
+    }
+
+    // 这个像素下一遍还要采多少个样本：第一遍采 min_samples 个，之后每遍 batch_samples 个
+    pub fn samples_needed(&self, stats: &PixelStats) -> u32 {
+        let max = self.settings.samples_per_pixel;
+        if stats.count >= max {
+            return 0;
+        }
+        let min = self.settings.min_samples.max(2).min(max);
+        if stats.count < min {
+            return min - stats.count;
+        }
+        if self.settings.adaptive() && stats.relative_error() <= self.settings.error_threshold {
+            return 0;
+        }
+        self.settings.batch_samples.max(1).min(max - stats.count)
+    }
+
+    // 样本按滤波器分摊到 tile 里，tile 要覆盖这个像素
+    pub fn sample_pixel<T: Hittable + 'static>(
+        &mut self,
+        world: &T,
+        x: u32,
+        y: u32,
+        samples: u32,
+        tile: &mut FilmTile,
+    ) {
+        let width = self.settings.width;
+        let height = self.settings.height;
+        let pixel_index = (y * width + x) as usize;
+        // 光线微分按最终可能的样本数缩放，不随自适应的中途样本数变化
+        let differential_scale =
+            utility::fmax(0.125, 1. / (self.settings.samples_per_pixel as f64).sqrt());
+        for _ in 0..samples {
+            // 样本编号接着已有的样本往后数，每个样本的随机数与它是在哪一遍采的无关
+            let index = self.stats[pixel_index].count as u64;
+            let sampler = self.sampler.as_mut();
+            sampler.start_sample(pixel_index as u64, index);
+            let (jitter_x, jitter_y) = sampler.get_2d();
+            let u = (x as f64 + jitter_x) / width as f64;
+            let v = (y as f64 + jitter_y) / height as f64;
+            let mut ray = Camera::get_ray_differential(
+                self.camera,
+                u,
+                v,
+                1. / width as f64,
+                1. / height as f64,
+                sampler,
+            );
+            ray.scale_differentials(differential_scale);
+            let color = Ray::ray_color(ray, world, self.settings.max_depth, sampler);
+            self.stats[pixel_index].add(color);
+            tile.add_sample(x as f64 + jitter_x, y as f64 + jitter_y, color);
+        }
+    }
+
+    // 总渲染时间，包括恢复之前的部分
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed_before + self.run_start.elapsed()
+    }
+
+    fn check_stop(&self) -> Option<StopReason> {
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            return Some(StopReason::Interrupted);
+        }
+        match self.settings.time_limit {
+            Some(limit) if self.elapsed() >= limit => Some(StopReason::TimeLimit),
+            _ => None,
+        }
+    }
+
+    // 实际采样的区域：裁剪区域向外扩展滤波器半径，让边上的像素也收到邻居的样本，
+    // 裁剪区域内的结果与渲染整张图时相同
+    pub fn sample_window(&self) -> CropWindow {
+        self.settings.crop.expand(
+            self.film.border(),
+            self.settings.width,
+            self.settings.height,
+        )
+    }
+
+    // 裁剪区域内的像素统计，从上到下逐行
+    fn crop_stats(&self) -> impl Iterator<Item = &PixelStats> {
+        let crop = self.settings.crop;
+        let (width, height) = (self.settings.width, self.settings.height);
+        (crop.y0..crop.y1).flat_map(move |row| {
+            let start = ((height - row - 1) * width) as usize;
+            self.stats[start + crop.x0 as usize..start + crop.x1 as usize].iter()
+        })
+    }
+
+    // 给所有还需要样本的像素补一遍样本，返回这一遍采样的像素数
+    // 每行作为一个 tile 采样后合并进 film；每行结束时检查时间和 Ctrl-C，中途停下时已有的样本仍然有效
+    pub fn render_pass<T: Hittable + 'static>(
+        &mut self,
+        world: &T,
+        progress: &ProgressBar,
+    ) -> Result<u32, StopReason> {
+        let width = self.settings.width;
+        let height = self.settings.height;
+        let window = self.sample_window();
+        progress.set_length((window.width() * window.height()) as u64);
+        progress.set_position(0);
+        let mut active = 0;
+        for row in window.y0..window.y1 {
+            let y = height - row - 1;
+            let mut tile = self.film.tile(window.x0, y, window.x1, y + 1);
+            for x in window.x0..window.x1 {
+                let n = self.samples_needed(&self.stats[(y * width + x) as usize]);
+                if n > 0 {
+                    self.sample_pixel(world, x, y, n, &mut tile);
+                    active += 1;
+                }
+                progress.inc(1);
+            }
+            self.film.merge(tile);
+            if let Some(reason) = self.check_stop() {
+                return Err(reason);
+            }
+        }
+        Ok(active)
+    }
+
+    // 一遍一遍地补样本，每遍结束后调用 on_pass（例如写出中间结果），直到满足某个停止条件
+    pub fn render<T, F>(&mut self, world: &T, progress: &ProgressBar, mut on_pass: F) -> StopReason
+    where
+        T: Hittable + 'static,
+        F: FnMut(&Self, u32),
+    {
+        self.run_start = Instant::now();
+        let mut passes = 0;
+        loop {
+            match self.render_pass(world, progress) {
+                Err(reason) => return reason,
+                Ok(0) => return StopReason::Converged,
+                Ok(_) => {}
+            }
+            passes += 1;
+            on_pass(self, passes);
+            if self.settings.target_noise > 0. && self.noise_level() <= self.settings.target_noise {
+                return StopReason::TargetNoise;
+            }
+        }
+    }
+
+    // 裁剪区域内的平均相对误差
+    pub fn noise_level(&self) -> f64 {
+        let crop = self.settings.crop;
+        let total: f64 = self.crop_stats().map(|s| s.relative_error()).sum();
+        total / (crop.width() * crop.height()) as f64
+    }
+
+    pub fn total_samples(&self) -> u64 {
+        self.crop_stats().map(|s| s.count as u64).sum()
+    }
+
+    // 裁剪区域内重建滤波后的线性辐亮度，不裁剪高光
+    pub fn framebuffer(&self) -> Framebuffer {
+        let crop = self.settings.crop;
+        let height = self.settings.height;
+        let mut fb = Framebuffer::new(crop.width(), crop.height());
+        for row in crop.y0..crop.y1 {
+            for x in crop.x0..crop.x1 {
+                let color = self.film.resolve(x, height - row - 1);
+                fb.set(x - crop.x0, row - crop.y0, color);
+            }
+        }
+        fb
+    }
+
+    // 裁剪区域内每个像素均值的方差，用于降噪；样本不足两个的像素给一个很大的值
+    pub fn variance_buffer(&self) -> Vec<f64> {
+        self.crop_stats()
+            .map(|stats| {
+                if stats.count < 2 {
+                    1e3
+                } else {
+                    stats.variance() / stats.count as f64
+                }
+            })
+            .collect()
+    }
+
+    // 裁剪区域内每个像素的样本数，从蓝（少）经绿到红（多）
+    pub fn sample_count_heatmap(&self) -> RgbImage {
+        let crop = self.settings.crop;
+        let width = self.settings.width;
+        let height = self.settings.height;
+        let max = self.settings.samples_per_pixel.max(1) as f64;
+        ImageBuffer::from_fn(crop.width(), crop.height(), |x, row| {
+            let (x, row) = (x + crop.x0, row + crop.y0);
+            let count = self.stats[((height - row - 1) * width + x) as usize].count;
+            let t = utility::clamp(count as f64 / max, 0., 1.);
+            let color = if t < 0.5 {
+                Vec3::new(0., 0., 1.) * (1. - 2. * t) + Vec3::new(0., 1., 0.) * (2. * t)
+            } else {
+                Vec3::new(0., 1., 0.) * (2. - 2. * t) + Vec3::new(1., 0., 0.) * (2. * t - 1.)
+            };
+            image::Rgb([
+                (color.x * 255.).round() as u8,
+                (color.y * 255.).round() as u8,
+                (color.z * 255.).round() as u8,
+            ])
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hittable::{sphere::Sphere, HittableList},
+        material::{dielectric::Dielectric, lambertian::Lambertian},
+        texture::noise::Fbm,
+    };
+    use std::rc::Rc;
+
+    pub(super) fn tiny_scene() -> HittableList {
+        let mut world = HittableList::default();
+        world.add(Sphere {
+            center: Vec3::new(0., -100.5, -1.),
+            r: 100.,
+            mat_ptr: Rc::new(Lambertian {
+                albedo: Rc::new(Fbm::new(4., 3)),
+            }),
+        });
+        world.add(Sphere {
+            center: Vec3::new(0., 0., -1.),
+            r: 0.5,
+            mat_ptr: Rc::new(Dielectric {
+                ref_idx: 1.5,
+                film: None,
+            }),
+        });
+        world
+    }
+
+    // 12x8 的小图，自适应采样，要渲染好几遍才停下
+    pub(super) fn tiny_settings(seed: u64) -> RenderSettings {
+        let (width, height) = (12, 8);
+        RenderSettings {
+            width,
+            height,
+            samples_per_pixel: 16,
+            max_depth: 8,
+            seed,
+            sampler: SamplerKind::Sobol,
+            error_threshold: 0.05,
+            min_samples: 4,
+            batch_samples: 4,
+            time_limit: None,
+            target_noise: 0.,
+            filter: PixelFilter::new(film::FilterKind::Mitchell),
+            crop: CropWindow::full(width, height),
+        }
+    }
+
+    pub(super) fn tiny_camera() -> Camera {
+        Camera::new(
+            Vec3::new(0., 0., 1.),
+            Vec3::new(0., 0., -1.),
+            Vec3::new(0., 1., 0.),
+            60.,
+            1.5,
+            0.1,
+            2.,
+            0.,
+            1.,
+        )
+    }
+
+    fn render_tiny(seed: u64) -> Framebuffer {
+        render_window(seed, tiny_settings(seed).crop)
+    }
+
+    fn render_window(seed: u64, crop: CropWindow) -> Framebuffer {
+        let settings = RenderSettings {
+            crop,
+            ..tiny_settings(seed)
+        };
+        let mut renderer = Renderer::new(settings, tiny_camera());
+        renderer.render(&tiny_scene(), &ProgressBar::hidden(), |_, _| {});
+        renderer.framebuffer()
+    }
+
+    fn bits(fb: &Framebuffer) -> Vec<u32> {
+        fb.pixels.iter().flatten().map(|c| c.to_bits()).collect()
+    }
+
+    // 同一种子两次渲染的结果逐位相同，换一个种子则不同
+    #[test]
+    fn same_seed_renders_identical_framebuffers() {
+        let first = render_tiny(7);
+        let second = render_tiny(7);
+        assert_eq!(bits(&first), bits(&second));
+        assert_ne!(bits(&first), bits(&render_tiny(8)));
+    }
+
+    // 渲染一遍后写出检查点，读回来接着渲染完，结果与不中断的渲染逐位相同
+    #[test]
+    fn resumed_render_matches_an_uninterrupted_one() {
+        let world = tiny_scene();
+        let progress = ProgressBar::hidden();
+        let mut first = Renderer::new(tiny_settings(3), tiny_camera());
+        assert!(first.render_pass(&world, &progress).unwrap() > 0);
+        let path = std::env::temp_dir().join(format!("raytracer-ckpt-{}.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        checkpoint::save(&first, path).unwrap();
+        let resumed = checkpoint::load(path, tiny_camera());
+        std::fs::remove_file(path).unwrap();
+        let mut resumed = resumed.unwrap();
+        assert_eq!(resumed.total_samples(), first.total_samples());
+        resumed.render(&world, &progress, |_, _| {});
+        assert_eq!(bits(&resumed.framebuffer()), bits(&render_tiny(3)));
+    }
+
+    // 分块采样后按相反的顺序合并，与整张图作为一个 tile 的结果相同，只差浮点加法的舍入
+    #[test]
+    fn tiles_merged_in_any_order_match_a_single_tile() {
+        let world = tiny_scene();
+        let (width, height) = (12, 8);
+        let render = |tiles: &[(u32, u32, u32, u32)]| {
+            let mut renderer = Renderer::new(tiny_settings(11), tiny_camera());
+            let mut sampled = Vec::new();
+            for &(x0, y0, x1, y1) in tiles {
+                let mut tile = renderer.film.tile(x0, y0, x1, y1);
+                for y in y0..y1 {
+                    for x in x0..x1 {
+                        renderer.sample_pixel(&world, x, y, 4, &mut tile);
+                    }
+                }
+                sampled.push(tile);
+            }
+            for tile in sampled.into_iter().rev() {
+                renderer.film.merge(tile);
+            }
+            renderer.film
+        };
+
+        let single = render(&[(0, 0, width, height)]);
+        let mut blocks = Vec::new();
+        for y0 in (0..height).step_by(3) {
+            for x0 in (0..width).step_by(5) {
+                blocks.push((x0, y0, (x0 + 5).min(width), (y0 + 3).min(height)));
+            }
+        }
+        let tiled = render(&blocks);
+        for (a, b) in single.pixels.iter().zip(tiled.pixels.iter()) {
+            assert!(a.weight > 0.);
+            assert!((a.sum - b.sum).length() <= 1e-12 * a.sum.length().max(1.));
+            assert!((a.weight - b.weight).abs() <= 1e-12 * a.weight.max(1.));
+        }
+    }
+
+    // 裁剪区域内的图片和 AOV 与整张图里对应的像素逐位相同
+    #[test]
+    fn crop_window_matches_the_full_render() {
+        let full_window = CropWindow::full(12, 8);
+        let crop = CropWindow::from_pixels([3., 2., 9., 6.], 12, 8).unwrap();
+        let full = render_window(5, full_window);
+        let cropped = render_window(5, crop);
+        assert_eq!((cropped.width, cropped.height), (6, 4));
+        for y in 0..crop.height() {
+            for x in 0..crop.width() {
+                let a = full.pixels[((y + crop.y0) * 12 + x + crop.x0) as usize];
+                let b = cropped.pixels[(y * crop.width() + x) as usize];
+                for c in 0..4 {
+                    assert_eq!(a[c].to_bits(), b[c].to_bits(), "pixel ({}, {})", x, y);
+                }
+            }
+        }
+
+        let world = tiny_scene();
+        let full = aov::render_aovs(&world, tiny_camera(), 12, 8, full_window, 5);
+        let cropped = aov::render_aovs(&world, tiny_camera(), 12, 8, crop, 5);
+        for &kind in &aov::AovKind::ALL {
+            for (a, b) in full
+                .channels(kind)
+                .iter()
+                .zip(cropped.channels(kind).iter())
+            {
+                for y in 0..crop.height() {
+                    for x in 0..crop.width() {
+                        let a = a.data[((y + crop.y0) * 12 + x + crop.x0) as usize];
+                        let b = b.data[(y * crop.width() + x) as usize];
+                        assert_eq!(a.to_bits(), b.to_bits(), "{:?} at ({}, {})", kind, x, y);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use image::{
+    imageops,
+    pnm::{PNMSubtype, SampleEncoding},
+    ImageError, ImageOutputFormat, RgbImage,
+};
+use std::{
+    fmt, fs,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::render::{exr, framebuffer::Framebuffer, tonemap::DisplayTransform, CropWindow};
+
+#[derive(Debug)]
+pub enum OutputError {
+    UnsupportedFormat(String),
+    Io(io::Error),
+    Image(ImageError),
+}
+
+impl fmt::Display for OutputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OutputError::UnsupportedFormat(path) => write!(
+                f,
+                "unsupported output format \"{}\" (use .png, .ppm, .bmp, .tga, .jpg, .exr or .hdr)",
+                path
+            ),
+            OutputError::Io(err) => write!(f, "{}", err),
+            OutputError::Image(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<io::Error> for OutputError {
+    fn from(err: io::Error) -> Self {
+        OutputError::Io(err)
+    }
+}
+
+impl From<ImageError> for OutputError {
+    fn from(err: ImageError) -> Self {
+        OutputError::Image(err)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OutputFormat {
+    Png,
+    Ppm,
+    Bmp,
+    Tga,
+    Jpeg(u8), // 质量 0 到 100
+    Exr,
+    Hdr,
+}
+
+impl OutputFormat {
+    // 由扩展名决定编码器，jpeg_quality 只对 JPEG 有效
+    pub fn from_path(path: &str, jpeg_quality: u8) -> Result<Self, OutputError> {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("png") => Ok(OutputFormat::Png),
+            Some("ppm") => Ok(OutputFormat::Ppm),
+            Some("bmp") => Ok(OutputFormat::Bmp),
+            Some("tga") => Ok(OutputFormat::Tga),
+            Some("jpg") | Some("jpeg") => Ok(OutputFormat::Jpeg(jpeg_quality)),
+            Some("exr") => Ok(OutputFormat::Exr),
+            Some("hdr") => Ok(OutputFormat::Hdr),
+            _ => Err(OutputError::UnsupportedFormat(path.to_string())),
+        }
+    }
+}
+
+// 先写到临时文件再改名，渲染中途被打断时磁盘上总有一张完整的图片
+pub fn replace_file<E, F>(path: &str, write: F) -> Result<(), E>
+where
+    E: From<io::Error>,
+    F: FnOnce(&str) -> Result<(), E>,
+{
+    let tmp_path = format!("{}.tmp", path);
+    write(&tmp_path)?;
+    fs::rename(&tmp_path, Path::new(path))?;
+    Ok(())
+}
+
+pub fn save_ldr(img: &RgbImage, path: &str, format: OutputFormat) -> Result<(), OutputError> {
+    let format = match format {
+        OutputFormat::Png => ImageOutputFormat::Png,
+        OutputFormat::Ppm => ImageOutputFormat::Pnm(PNMSubtype::Pixmap(SampleEncoding::Binary)),
+        OutputFormat::Bmp => ImageOutputFormat::Bmp,
+        OutputFormat::Tga => ImageOutputFormat::Tga,
+        OutputFormat::Jpeg(quality) => ImageOutputFormat::Jpeg(quality),
+        OutputFormat::Exr | OutputFormat::Hdr => {
+            return Err(OutputError::UnsupportedFormat(path.to_string()))
+        }
+    };
+    replace_file(path, |tmp_path| {
+        let mut file = BufWriter::new(File::create(tmp_path)?);
+        image::DynamicImage::ImageRgb8(img.clone()).write_to(&mut file, format)?;
+        file.flush()?;
+        Ok(())
+    })
+}
+
+// 浮点格式直接写线性数据，其它格式写经过显示变换的 8 位图片
+pub fn save(
+    fb: &Framebuffer,
+    path: &str,
+    format: OutputFormat,
+    display: &DisplayTransform,
+) -> Result<(), OutputError> {
+    match format {
+        OutputFormat::Exr => save_exr(path, fb.width, fb.height, &exr::framebuffer_channels(fb))?,
+        OutputFormat::Hdr => replace_file(path, |tmp_path| save_hdr(fb, tmp_path))?,
+        _ => save_ldr(&fb.to_rgb8(display), path, format)?,
+    }
+    Ok(())
+}
+
+// 把只渲染了 window 部分的结果贴到之前保存的整张图 base_path 上，再写到 path
+// 浮点格式的底图必须是 EXR，extra 是同时要贴上去的其它图层；8 位格式按显示变换后的像素替换
+pub fn save_overlay(
+    fb: &Framebuffer,
+    extra: Vec<exr::ExrChannel>,
+    window: CropWindow,
+    base_path: &str,
+    path: &str,
+    format: OutputFormat,
+    display: &DisplayTransform,
+) -> Result<(), OutputError> {
+    match format {
+        OutputFormat::Exr => {
+            let mut channels = exr::framebuffer_channels(fb);
+            channels.extend(extra);
+            save_exr_overlay(base_path, path, &channels, window)?;
+        }
+        OutputFormat::Hdr => {
+            let mut base = exr::read_exr(base_path)?;
+            base.paste(
+                &exr::framebuffer_channels(fb),
+                window.x0,
+                window.y0,
+                window.width(),
+            )?;
+            let full = base.framebuffer("")?;
+            replace_file(path, |tmp_path| save_hdr(&full, tmp_path))?;
+        }
+        _ => {
+            let mut base = image::open(base_path)?.to_rgb8();
+            if window.x1 > base.width() || window.y1 > base.height() {
+                return Err(OutputError::Io(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("\"{}\" is too small for the crop window", base_path),
+                )));
+            }
+            imageops::replace(&mut base, &fb.to_rgb8(display), window.x0, window.y0);
+            save_ldr(&base, path, format)?;
+        }
+    }
+    Ok(())
+}
+
+// 把 window 部分的通道贴到之前保存的 EXR 上，其它通道保持不变
+pub fn save_exr_overlay(
+    base_path: &str,
+    path: &str,
+    channels: &[exr::ExrChannel],
+    window: CropWindow,
+) -> Result<(), OutputError> {
+    let mut base = exr::read_exr(base_path)?;
+    base.paste(channels, window.x0, window.y0, window.width())?;
+    save_exr(path, base.width, base.height, &base.channels)
+}
+
+pub fn save_exr(
+    path: &str,
+    width: u32,
+    height: u32,
+    channels: &[exr::ExrChannel],
+) -> Result<(), OutputError> {
+    replace_file(path, |tmp_path| {
+        exr::write_exr(tmp_path, width, height, channels)?;
+        Ok(())
+    })
+}
+
+// 与 path 同目录、同名的附属文件，例如 output/output.jpg 的 albedo 为 output/output.albedo.exr
+pub fn sibling_path(path: &str, name: &str, extension: &str) -> String {
+    let path = Path::new(path);
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+    path.with_file_name(format!("{}.{}.{}", stem, name, extension))
+        .to_string_lossy()
+        .into_owned()
+}
+
+// 动画第 frame 帧的文件名：path 里的一串 # 换成补零的帧号，例如 frame_####.png 的第 7 帧为 frame_0007.png；
+// 没有 # 时在扩展名前加上四位帧号，例如 output/output.0007.jpg
+pub fn numbered_path(path: &str, frame: u32) -> String {
+    if let Some(start) = path.find('#') {
+        let digits = path[start..].chars().take_while(|c| *c == '#').count();
+        return format!(
+            "{}{:0width$}{}",
+            &path[..start],
+            frame,
+            &path[start + digits..],
+            width = digits
+        );
+    }
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("");
+    sibling_path(path, &format!("{:04}", frame), extension)
+}
+
+// Radiance RGBE (.hdr)，不做游程编码
+pub fn save_hdr(fb: &Framebuffer, path: &str) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    write!(
+        file,
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+        fb.height, fb.width
+    )?;
+    for p in &fb.pixels {
+        file.write_all(&rgbe(p[0], p[1], p[2]))?;
+    }
+    file.flush()
+}
+
+// 三个通道共用最大分量的指数
+fn rgbe(r: f32, g: f32, b: f32) -> [u8; 4] {
+    let v = r.max(g).max(b);
+    if v < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+    let exponent = v.log2().floor() as i32 + 1; // v / 2^exponent 落在 [0.5, 1)
+    let scale = 256. / 2f32.powi(exponent);
+    let byte = |c: f32| (c.max(0.) * scale).min(255.) as u8;
+    [byte(r), byte(g), byte(b), (exponent + 128) as u8]
+}
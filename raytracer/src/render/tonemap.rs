@@ -0,0 +1,297 @@
+use crate::{basic::vec::Vec3, utility};
+
+// 把线性辐亮度转为显示用的 sRGB：曝光、白平衡、色调映射、sRGB 编码
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ToneMapOperator {
+    Clamp, // 不压缩高光，超过 1 的直接截断
+    Reinhard,
+    ReinhardExtended { white: f64 }, // 亮度为 white 的地方映射到纯白
+    Hable,
+    Aces,
+    Agx,
+}
+
+impl ToneMapOperator {
+    // 名字不区分大小写，extended Reinhard 可以写成 "reinhard-extended:8" 指定白点
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        if let Some(white) = name.strip_prefix("reinhard-extended") {
+            let white = match white.strip_prefix(':') {
+                Some(value) => value.parse().ok()?,
+                None if white.is_empty() => 4.,
+                None => return None,
+            };
+            return Some(ToneMapOperator::ReinhardExtended { white });
+        }
+        match name.as_str() {
+            "clamp" | "none" => Some(ToneMapOperator::Clamp),
+            "reinhard" => Some(ToneMapOperator::Reinhard),
+            "hable" | "uncharted" => Some(ToneMapOperator::Hable),
+            "aces" => Some(ToneMapOperator::Aces),
+            "agx" => Some(ToneMapOperator::Agx),
+            _ => None,
+        }
+    }
+
+    // 输入曝光后的线性颜色，输出 [0, 1] 内的线性颜色
+    pub fn apply(self, color: Vec3) -> Vec3 {
+        let color = Vec3::new(color.x.max(0.), color.y.max(0.), color.z.max(0.));
+        let mapped = match self {
+            ToneMapOperator::Clamp => color,
+            // Reinhard 作用在亮度上，保持色相
+            ToneMapOperator::Reinhard => {
+                let l = utility::luminance(color);
+                color / (1. + l)
+            }
+            ToneMapOperator::ReinhardExtended { white } => {
+                let l = utility::luminance(color);
+                if l <= 0. {
+                    color
+                } else {
+                    let mapped = l * (1. + l / (white * white)) / (1. + l);
+                    color * (mapped / l)
+                }
+            }
+            ToneMapOperator::Hable => {
+                const WHITE: f64 = 11.2;
+                let scale = 1. / hable_partial(WHITE);
+                let exposure_bias = 2.;
+                Vec3::new(
+                    hable_partial(color.x * exposure_bias) * scale,
+                    hable_partial(color.y * exposure_bias) * scale,
+                    hable_partial(color.z * exposure_bias) * scale,
+                )
+            }
+            ToneMapOperator::Aces => aces_fitted(color),
+            ToneMapOperator::Agx => agx(color),
+        };
+        Vec3::new(
+            utility::clamp(mapped.x, 0., 1.),
+            utility::clamp(mapped.y, 0., 1.),
+            utility::clamp(mapped.z, 0., 1.),
+        )
+    }
+}
+
+// John Hable 的 Uncharted 2 曲线
+fn hable_partial(x: f64) -> f64 {
+    const A: f64 = 0.15;
+    const B: f64 = 0.50;
+    const C: f64 = 0.10;
+    const D: f64 = 0.20;
+    const E: f64 = 0.02;
+    const F: f64 = 0.30;
+    (x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F) - E / F
+}
+
+fn mat_mul(m: &[[f64; 3]; 3], v: Vec3) -> Vec3 {
+    Vec3::new(
+        m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+        m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+        m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+    )
+}
+
+// Stephen Hill 对 ACES RRT + ODT 的拟合，输入输出都是线性 sRGB
+fn aces_fitted(color: Vec3) -> Vec3 {
+    const INPUT: [[f64; 3]; 3] = [
+        [0.59719, 0.35458, 0.04823],
+        [0.07600, 0.90834, 0.01566],
+        [0.02840, 0.13383, 0.83777],
+    ];
+    const OUTPUT: [[f64; 3]; 3] = [
+        [1.60475, -0.53108, -0.07367],
+        [-0.10208, 1.10813, -0.00605],
+        [-0.00327, -0.07276, 1.07602],
+    ];
+    let v = mat_mul(&INPUT, color);
+    let fit =
+        |x: f64| (x * (x + 0.0245786) - 0.000090537) / (x * (0.983729 * x + 0.432951) + 0.238081);
+    mat_mul(&OUTPUT, Vec3::new(fit(v.x), fit(v.y), fit(v.z)))
+}
+
+// AgX 的多项式近似（Benjamin Wrensch），曲线输出的是显示编码值，最后转回线性
+fn agx(color: Vec3) -> Vec3 {
+    const INSET: [[f64; 3]; 3] = [
+        [0.842479062253094, 0.0784335999999992, 0.0792237451477643],
+        [0.0423282422610123, 0.878468636469772, 0.0791661274605434],
+        [0.0423756549057051, 0.0784336, 0.879142973793104],
+    ];
+    const OUTSET: [[f64; 3]; 3] = [
+        [1.19687900512017, -0.0980208811401368, -0.0990297440797205],
+        [-0.0528968517574562, 1.15190312990417, -0.0989611768448433],
+        [-0.0529716355144438, -0.0980434501171241, 1.15107367264116],
+    ];
+    const MIN_EV: f64 = -12.47393;
+    const MAX_EV: f64 = 4.026069;
+    let contrast = |x: f64| {
+        let x = (utility::clamp(x.max(1e-10).log2(), MIN_EV, MAX_EV) - MIN_EV) / (MAX_EV - MIN_EV);
+        let x2 = x * x;
+        let x4 = x2 * x2;
+        15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x
+            - 0.00232
+    };
+    let v = mat_mul(&INSET, color);
+    let v = mat_mul(
+        &OUTSET,
+        Vec3::new(contrast(v.x), contrast(v.y), contrast(v.z)),
+    );
+    Vec3::new(
+        v.x.max(0.).powf(2.2),
+        v.y.max(0.).powf(2.2),
+        v.z.max(0.).powf(2.2),
+    )
+}
+
+// 黑体在色温 kelvin 下的颜色（线性 sRGB，亮度为 1），普朗克轨迹用 Kim 等人的三次拟合
+pub fn blackbody_white(kelvin: f64) -> Vec3 {
+    let t = utility::clamp(kelvin, 1667., 25000.);
+    let x = if t <= 4000. {
+        -0.2661239e9 / t.powi(3) - 0.2343589e6 / t.powi(2) + 0.8776956e3 / t + 0.179910
+    } else {
+        -3.0258469e9 / t.powi(3) + 2.1070379e6 / t.powi(2) + 0.2226347e3 / t + 0.240390
+    };
+    let y = if t <= 2222. {
+        -1.1063814 * x.powi(3) - 1.34811020 * x * x + 2.18555832 * x - 0.20219683
+    } else if t <= 4000. {
+        -0.9549476 * x.powi(3) - 1.37418593 * x * x + 2.09137015 * x - 0.16748867
+    } else {
+        3.0817580 * x.powi(3) - 5.87338670 * x * x + 3.75112997 * x - 0.37001483
+    };
+    const XYZ_TO_SRGB: [[f64; 3]; 3] = [
+        [3.2404542, -1.5371385, -0.4985314],
+        [-0.9692660, 1.8760108, 0.0415560],
+        [0.0556434, -0.2040259, 1.0572252],
+    ];
+    mat_mul(&XYZ_TO_SRGB, Vec3::new(x / y, 1., (1. - x - y) / y))
+}
+
+// 线性值编码为 sRGB
+pub fn srgb_oetf(x: f64) -> f64 {
+    if x <= 0.0031308 {
+        12.92 * x
+    } else {
+        1.055 * x.powf(1. / 2.4) - 0.055
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct DisplayTransform {
+    pub exposure: f64,       // 曝光补偿，单位 EV
+    pub white_balance: Vec3, // 每个通道的增益
+    pub operator: ToneMapOperator,
+}
+
+impl Default for DisplayTransform {
+    fn default() -> Self {
+        Self {
+            exposure: 0.,
+            white_balance: Vec3::ones(),
+            operator: ToneMapOperator::Clamp,
+        }
+    }
+}
+
+impl DisplayTransform {
+    // 让色温为 kelvin 的光源显示为白色，6500K 左右不改变颜色
+    pub fn white_balance_from_temperature(kelvin: f64) -> Vec3 {
+        let reference = blackbody_white(6504.);
+        let white = blackbody_white(kelvin);
+        // 色温很低时蓝色分量接近 0，限制增益
+        let gain = Vec3::new(
+            reference.x / white.x.max(1e-3),
+            reference.y / white.y.max(1e-3),
+            reference.z / white.z.max(1e-3),
+        );
+        gain / utility::luminance(gain)
+    }
+
+    // 返回 [0, 1] 内的显示值
+    pub fn apply(&self, color: Vec3) -> Vec3 {
+        let exposed = color.elemul(self.white_balance) * 2f64.powf(self.exposure);
+        let mapped = self.operator.apply(exposed);
+        Vec3::new(
+            srgb_oetf(mapped.x),
+            srgb_oetf(mapped.y),
+            srgb_oetf(mapped.z),
+        )
+    }
+
+    pub fn rgb8(&self, color: Vec3) -> [u8; 3] {
+        let c = self.apply(color);
+        [
+            (c.x * 255.).round() as u8,
+            (c.y * 255.).round() as u8,
+            (c.z * 255.).round() as u8,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64, eps: f64) -> bool {
+        (a - b).abs() <= eps
+    }
+
+    #[test]
+    fn srgb_oetf_matches_the_standard() {
+        assert!(close(srgb_oetf(0.), 0., 1e-12));
+        // 分段点两侧的值连续
+        assert!(close(srgb_oetf(0.0031308), 0.0031308 * 12.92, 1e-12));
+        assert!(close(
+            1.055 * 0.0031308f64.powf(1. / 2.4) - 0.055,
+            0.0031308 * 12.92,
+            1e-6
+        ));
+        assert!(close(srgb_oetf(1.), 1., 1e-12));
+    }
+
+    #[test]
+    fn operators_map_black_to_black_and_stay_in_range() {
+        let operators = [
+            ToneMapOperator::Clamp,
+            ToneMapOperator::Reinhard,
+            ToneMapOperator::ReinhardExtended { white: 4. },
+            ToneMapOperator::Hable,
+            ToneMapOperator::Aces,
+            ToneMapOperator::Agx,
+        ];
+        for &operator in &operators {
+            let black = operator.apply(Vec3::zero());
+            assert!(
+                black.x.abs() < 1e-9 && black.y.abs() < 1e-9 && black.z.abs() < 1e-9,
+                "{:?} does not map 0 to 0",
+                operator
+            );
+            let mut level = 1e-4;
+            while level < 1e4 {
+                for &color in &[
+                    Vec3::ones() * level,
+                    Vec3::new(level, 0.1 * level, 0.),
+                    Vec3::new(0., 0.3 * level, 2. * level),
+                ] {
+                    let c = operator.apply(color);
+                    for v in &[c.x, c.y, c.z] {
+                        assert!(
+                            (0. ..=1.).contains(v),
+                            "{:?} maps {} outside [0, 1]",
+                            operator,
+                            v
+                        );
+                    }
+                }
+                level *= 3.;
+            }
+        }
+    }
+
+    #[test]
+    fn d65_white_balance_is_neutral() {
+        let gain = DisplayTransform::white_balance_from_temperature(6504.);
+        assert!(close(gain.x, 1., 1e-9));
+        assert!(close(gain.y, 1., 1e-9));
+        assert!(close(gain.z, 1., 1e-9));
+    }
+}
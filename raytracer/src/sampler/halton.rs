@@ -0,0 +1,67 @@
+use rand::Rng;
+
+use super::{to_unit, SampleState, Sampler};
+use crate::utility::RandGen;
+
+const PRIMES: [u64; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131,
+];
+
+// Halton 序列：第 d 维取以第 d 个素数为底的根式反演
+// 每个像素每一维加一个由哈希决定的偏移 (Cranley-Patterson rotation)，避免相邻像素的样本完全一样
+// 超过 32 维后退化为独立采样
+pub struct HaltonSampler {
+    pub state: SampleState,
+}
+
+impl HaltonSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: SampleState::new(seed),
+        }
+    }
+
+    fn sample(&mut self) -> f64 {
+        let dimension = self.state.dimension as usize;
+        let offset = (self.state.next_dimension_hash() >> 11) as f64 / (1u64 << 53) as f64;
+        if dimension >= PRIMES.len() {
+            return self.state.rng.gen();
+        }
+        let x = radical_inverse(PRIMES[dimension], self.state.index) + offset;
+        to_unit(x - x.floor())
+    }
+}
+
+// 把 index 按 base 进制的各位数字倒序放到小数点后
+pub fn radical_inverse(base: u64, mut index: u64) -> f64 {
+    let inv_base = 1. / base as f64;
+    let mut inv_base_n = 1.;
+    let mut reversed: u64 = 0;
+    while index > 0 {
+        let next = index / base;
+        reversed = reversed * base + (index - next * base);
+        inv_base_n *= inv_base;
+        index = next;
+    }
+    reversed as f64 * inv_base_n
+}
+
+impl Sampler for HaltonSampler {
+    fn start_sample(&mut self, pixel: u64, index: u64) {
+        self.state.start(pixel, index);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        self.sample()
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let x = self.sample();
+        (x, self.sample())
+    }
+
+    fn rng(&mut self) -> &mut RandGen {
+        &mut self.state.rng
+    }
+}
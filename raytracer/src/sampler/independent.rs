@@ -0,0 +1,35 @@
+use rand::Rng;
+
+use super::{SampleState, Sampler};
+use crate::utility::RandGen;
+
+// 每一维都是独立的均匀随机数
+pub struct IndependentSampler {
+    pub state: SampleState,
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: SampleState::new(seed),
+        }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn start_sample(&mut self, pixel: u64, index: u64) {
+        self.state.start(pixel, index);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        self.state.rng.gen()
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.state.rng.gen(), self.state.rng.gen())
+    }
+
+    fn rng(&mut self) -> &mut RandGen {
+        &mut self.state.rng
+    }
+}
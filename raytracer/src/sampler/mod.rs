@@ -0,0 +1,126 @@
+pub mod halton;
+pub mod independent;
+pub mod sobol;
+pub mod stratified;
+
+use crate::utility::{self, RandGen};
+
+// 为每个样本提供若干维 [0, 1) 内的随机数：像素内位置、镜头、快门时间、每次弹射的方向……
+// 按调用顺序依次消耗维度，好的采样器让同一像素的各个样本在每一维上分布均匀
+pub trait Sampler {
+    // 开始像素 pixel 的第 index 个样本，维度从 0 重新计数
+    fn start_sample(&mut self, pixel: u64, index: u64);
+    fn get_1d(&mut self) -> f64;
+    fn get_2d(&mut self) -> (f64, f64);
+    // 拒绝采样等不适合低差异序列的地方使用的随机数生成器，同样只由种子和样本编号决定
+    fn rng(&mut self) -> &mut RandGen;
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SamplerKind {
+    Independent,
+    Stratified,
+    Halton,
+    Sobol,
+}
+
+impl SamplerKind {
+    pub fn create(self, seed: u64, samples_per_pixel: u32) -> Box<dyn Sampler> {
+        match self {
+            SamplerKind::Independent => Box::new(independent::IndependentSampler::new(seed)),
+            SamplerKind::Stratified => {
+                Box::new(stratified::StratifiedSampler::new(seed, samples_per_pixel))
+            }
+            SamplerKind::Halton => Box::new(halton::HaltonSampler::new(seed)),
+            SamplerKind::Sobol => Box::new(sobol::SobolSampler::new(seed)),
+        }
+    }
+}
+
+// 各采样器共用的状态：当前像素、样本编号、已用掉的维度和后备的随机数生成器
+pub struct SampleState {
+    pub seed: u64,
+    pub pixel: u64,
+    pub index: u64,
+    pub dimension: u64,
+    pub rng: RandGen,
+}
+
+impl SampleState {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pixel: 0,
+            index: 0,
+            dimension: 0,
+            rng: utility::sample_rng(seed, 0, 0),
+        }
+    }
+
+    pub fn start(&mut self, pixel: u64, index: u64) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+        self.rng = utility::sample_rng(self.seed, pixel, index);
+    }
+
+    // 当前维度在当前像素下的哈希值，用于打乱和扰动；调用后维度加一
+    pub fn next_dimension_hash(&mut self) -> u64 {
+        let hash = utility::hash_u64(
+            self.seed ^ utility::hash_u64(self.pixel ^ utility::hash_u64(self.dimension)),
+        );
+        self.dimension += 1;
+        hash
+    }
+}
+
+// 把 [0, 1) 内的浮点数限制在 1 以下
+pub fn to_unit(x: f64) -> f64 {
+    x.min(1. - f64::EPSILON / 2.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 每个像素用 spp 个样本估计三角形 x + y < 1 的面积（真值 0.5），返回各像素估计值的均方根误差
+    // 前一个二维留给像素内位置，用后面的维度积分，检验的不只是第一对维度
+    fn triangle_rmse(kind: SamplerKind, spp: u32) -> f64 {
+        let mut sampler = kind.create(1, spp);
+        let pixels = 1000;
+        let mut sum_sq = 0.;
+        for pixel in 0..pixels {
+            let mut hits = 0;
+            for index in 0..spp {
+                sampler.start_sample(pixel, index as u64);
+                sampler.get_2d();
+                let (x, y) = sampler.get_2d();
+                if x + y < 1. {
+                    hits += 1;
+                }
+            }
+            let error = hits as f64 / spp as f64 - 0.5;
+            sum_sq += error * error;
+        }
+        (sum_sq / pixels as f64).sqrt()
+    }
+
+    #[test]
+    fn low_discrepancy_samplers_converge_faster() {
+        let independent = triangle_rmse(SamplerKind::Independent, 64);
+        for &kind in &[
+            SamplerKind::Stratified,
+            SamplerKind::Halton,
+            SamplerKind::Sobol,
+        ] {
+            let rmse = triangle_rmse(kind, 64);
+            assert!(
+                rmse < 0.5 * independent,
+                "{:?}: RMSE {} vs independent {}",
+                kind,
+                rmse,
+                independent
+            );
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use super::{to_unit, SampleState, Sampler};
+use crate::utility::RandGen;
+
+// Owen 扰动的 Sobol 序列，做法见 Burley, "Practical Hash-based Owen Scrambling", 2020
+// 每两维取 Sobol 序列的前两维，样本编号先按维度做嵌套均匀打乱，使不同维度组之间不相关，
+// 再对结果做 Owen 扰动；样本数取 2 的幂时分层效果最好
+pub struct SobolSampler {
+    pub state: SampleState,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: SampleState::new(seed),
+        }
+    }
+}
+
+// Sobol 序列第 0 维（van der Corput 序列）和第 1 维
+fn sobol(index: u32, dimension: usize) -> u32 {
+    let mut x = 0;
+    let mut v: u32 = 1 << 31;
+    let mut i = index;
+    while i != 0 {
+        if i & 1 != 0 {
+            x ^= v;
+        }
+        v = if dimension == 0 { v >> 1 } else { v ^ (v >> 1) };
+        i >>= 1;
+    }
+    x
+}
+
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50_b47c);
+    x ^= x.wrapping_mul(0xb82f_1e52);
+    x ^= x.wrapping_mul(0xc7af_e638);
+    x ^= x.wrapping_mul(0x8d22_f6e6);
+    x
+}
+
+pub fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+fn hash_combine(seed: u32, v: u32) -> u32 {
+    seed ^ (v
+        .wrapping_add(seed << 6)
+        .wrapping_add(seed >> 2)
+        .wrapping_add(0x9e37_79b9))
+}
+
+impl Sampler for SobolSampler {
+    fn start_sample(&mut self, pixel: u64, index: u64) {
+        self.state.start(pixel, index);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let seed = self.state.next_dimension_hash() as u32;
+        let index = nested_uniform_scramble(self.state.index as u32, seed);
+        let x = nested_uniform_scramble(sobol(index, 0), hash_combine(seed, 0));
+        to_unit(x as f64 / 4_294_967_296.)
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let seed = self.state.next_dimension_hash() as u32;
+        self.state.dimension += 1;
+        let index = nested_uniform_scramble(self.state.index as u32, seed);
+        let x = nested_uniform_scramble(sobol(index, 0), hash_combine(seed, 0));
+        let y = nested_uniform_scramble(sobol(index, 1), hash_combine(seed, 1));
+        (
+            to_unit(x as f64 / 4_294_967_296.),
+            to_unit(y as f64 / 4_294_967_296.),
+        )
+    }
+
+    fn rng(&mut self) -> &mut RandGen {
+        &mut self.state.rng
+    }
+}
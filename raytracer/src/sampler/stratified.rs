@@ -0,0 +1,119 @@
+use rand::Rng;
+
+use super::{to_unit, SampleState, Sampler};
+use crate::utility::RandGen;
+
+// 分层采样：每一维把 [0, 1) 分成 samples_per_pixel 层，每个样本落在不同的层里
+// 二维用 Kensler 的相关多重抖动 (correlated multi-jittered) 采样，行列同时分层
+// 每个像素每一维的层顺序都用哈希打乱，样本编号超过 samples_per_pixel 时退化为独立采样
+pub struct StratifiedSampler {
+    pub state: SampleState,
+    pub samples_per_pixel: u32,
+}
+
+impl StratifiedSampler {
+    pub fn new(seed: u64, samples_per_pixel: u32) -> Self {
+        Self {
+            state: SampleState::new(seed),
+            samples_per_pixel: samples_per_pixel.max(1),
+        }
+    }
+}
+
+// Kensler, "Correlated Multi-Jittered Sampling", 2013：不需要存储的随机排列
+pub fn permute(mut i: u32, l: u32, p: u32) -> u32 {
+    let mut w = l - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < l {
+            break;
+        }
+    }
+    (i + p) % l
+}
+
+pub fn randfloat(mut i: u32, p: u32) -> f64 {
+    i ^= p;
+    i ^= i >> 17;
+    i ^= i >> 10;
+    i = i.wrapping_mul(0xb365_34e5);
+    i ^= i >> 12;
+    i ^= i >> 21;
+    i = i.wrapping_mul(0x93fc_4795);
+    i ^= 0xdf6e_307f;
+    i ^= i >> 17;
+    i = i.wrapping_mul(1 | p >> 18);
+    i as f64 / 4_294_967_296.
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_sample(&mut self, pixel: u64, index: u64) {
+        self.state.start(pixel, index);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let seed = self.state.next_dimension_hash() as u32;
+        let count = self.samples_per_pixel;
+        if self.state.index >= count as u64 {
+            return self.state.rng.gen();
+        }
+        let stratum = permute(
+            self.state.index as u32,
+            count,
+            seed.wrapping_mul(0x68bc_21eb),
+        );
+        to_unit(
+            (stratum as f64 + randfloat(stratum, seed.wrapping_mul(0x967a_889b))) / count as f64,
+        )
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let seed = self.state.next_dimension_hash() as u32;
+        self.state.dimension += 1;
+        let count = self.samples_per_pixel;
+        if self.state.index >= count as u64 {
+            return (self.state.rng.gen(), self.state.rng.gen());
+        }
+        // 选取最接近正方形的 m x k 网格
+        let m = ((count as f64).sqrt() as u32).max(1);
+        let k = (count - 1) / m + 1;
+        let stratum = permute(
+            self.state.index as u32,
+            count,
+            seed.wrapping_mul(0x5163_3e2d),
+        );
+        let sx = permute(stratum % m, m, seed.wrapping_mul(0xa511_e9b3));
+        let sy = permute(stratum / m, k, seed.wrapping_mul(0x63d8_3595));
+        let jx = randfloat(stratum, seed.wrapping_mul(0xa399_d265));
+        let jy = randfloat(stratum, seed.wrapping_mul(0x711a_d6a5));
+        let x = (stratum % m) as f64 + (sy as f64 + jx) / k as f64;
+        let y = (stratum / m) as f64 + (sx as f64 + jy) / m as f64;
+        (to_unit(x / m as f64), to_unit(y / k as f64))
+    }
+
+    fn rng(&mut self) -> &mut RandGen {
+        &mut self.state.rng
+    }
+}
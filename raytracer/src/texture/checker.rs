@@ -1,18 +1,113 @@
-use super::Texture;
-use crate::basic::vec::Vec3;
-use std::rc::Rc;
+use super::{Footprint, Texture};
+use crate::{basic::vec::Vec3, utility};
+use std::{f64::consts::PI, rc::Rc};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum CheckerMode {
+    World, // 按世界坐标划分立体格子
+    Uv,    // 按纹理坐标划分，物体移动时格子跟着走
+}
+
 pub struct Checker {
     pub odd: Rc<dyn Texture>,
     pub even: Rc<dyn Texture>,
+    pub frequency: f64, // 单位长度（或单位纹理坐标）内的格子数
+    pub mode: CheckerMode,
+}
+
+impl Checker {
+    fn sines(&self, u: f64, v: f64, p: Vec3) -> f64 {
+        let f = PI * self.frequency;
+        match self.mode {
+            CheckerMode::World => (f * p.x).sin() * (f * p.y).sin() * (f * p.z).sin(),
+            CheckerMode::Uv => (f * u).sin() * (f * v).sin(),
+        }
+    }
 }
 
 impl Texture for Checker {
     fn get_color_value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
-        let sines = (10. * p.x).sin() * (10. * p.y).sin() * (10. * p.z).sin();
-        if sines < 0. {
+        if self.sines(u, v, p) < 0. {
             self.odd.get_color_value(u, v, p)
         } else {
             self.even.get_color_value(u, v, p)
         }
     }
+
+    fn get_filtered_value(&self, u: f64, v: f64, p: Vec3, footprint: &Footprint) -> Vec3 {
+        // 足迹覆盖的格子数越多，越接近两种颜色的平均值
+        let width = match self.mode {
+            CheckerMode::World => footprint.width(),
+            CheckerMode::Uv => (footprint.dudx.abs() + footprint.dudy.abs())
+                .max(footprint.dvdx.abs() + footprint.dvdy.abs()),
+        };
+        let cells = width * self.frequency;
+        let fade = utility::clamp(2. * cells - 1., 0., 1.);
+        let odd = self.odd.get_filtered_value(u, v, p, footprint);
+        let even = self.even.get_filtered_value(u, v, p, footprint);
+        let sharp = if self.sines(u, v, p) < 0. { odd } else { even };
+        sharp * (1. - fade) + (odd + even) * 0.5 * fade
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::solid_color::SolidColor;
+
+    fn checker(frequency: f64, mode: CheckerMode) -> Checker {
+        Checker {
+            odd: Rc::new(SolidColor::new(0., 0., 0.)),
+            even: Rc::new(SolidColor::new(1., 1., 1.)),
+            frequency,
+            mode,
+        }
+    }
+
+    // frequency 为 4 时纹理坐标每 0.25 换一格
+    #[test]
+    fn uv_cells_follow_the_frequency() {
+        let texture = checker(4., CheckerMode::Uv);
+        let value = |u, v| texture.get_color_value(u, v, Vec3::zero()).x;
+        assert!(value(0.1, 0.1) == 1.);
+        assert!(value(0.35, 0.1) == 0.);
+        assert!(value(0.35, 0.4) == 1.);
+        assert!(value(0.6, 0.1) == 1.);
+        assert!(value(0.1, 0.9) == 0.);
+    }
+
+    // 世界坐标模式忽略纹理坐标，按 p 划分立体格子
+    #[test]
+    fn world_cells_follow_the_position() {
+        let texture = checker(2., CheckerMode::World);
+        let value = |p| texture.get_color_value(0.1, 0.1, p).x;
+        let p = Vec3::new(0.2, 0.2, 0.2);
+        assert!(value(p) == 1.);
+        assert!(value(p + Vec3::new(0.5, 0., 0.)) == 0.);
+        assert!(value(p + Vec3::new(0.5, 0.5, 0.)) == 1.);
+        assert!(value(p + Vec3::new(0.5, 0.5, 0.5)) == 0.);
+    }
+
+    // 足迹覆盖一格以上时得到两种颜色的平均，足迹很小时与不滤波相同
+    #[test]
+    fn wide_footprints_average_the_cells() {
+        let texture = checker(4., CheckerMode::Uv);
+        let wide = Footprint {
+            dudx: 0.25,
+            dvdy: 0.25,
+            ..Default::default()
+        };
+        let narrow = Footprint {
+            dudx: 0.01,
+            dvdy: 0.01,
+            ..Default::default()
+        };
+        assert!(texture.get_filtered_value(0.1, 0.1, Vec3::zero(), &wide).x == 0.5);
+        assert!(
+            texture
+                .get_filtered_value(0.35, 0.1, Vec3::zero(), &narrow)
+                .x
+                == 0.
+        );
+    }
 }
@@ -0,0 +1,273 @@
+use super::{Footprint, Texture};
+use crate::{basic::vec::Vec3, utility};
+
+// 各向异性过滤时沿长轴最多采样的次数
+const MAX_ANISOTROPY: usize = 8;
+
+// 纹理坐标超出 [0, 1] 时的处理方式
+// 场景里目前只用到 Repeat
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq)]
+pub enum WrapMode {
+    Repeat,
+    Clamp,
+    Mirror,
+}
+
+impl WrapMode {
+    fn wrap(self, i: i64, n: i64) -> usize {
+        let i = match self {
+            WrapMode::Repeat => i.rem_euclid(n),
+            WrapMode::Clamp => i.max(0).min(n - 1),
+            WrapMode::Mirror => {
+                let m = i.rem_euclid(2 * n);
+                if m < n {
+                    m
+                } else {
+                    2 * n - 1 - m
+                }
+            }
+        };
+        i as usize
+    }
+}
+
+// MIP 金字塔中的一层
+pub struct MipLevel {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<Vec3>, // 线性颜色，逐行从上到下存储
+}
+
+impl MipLevel {
+    fn texel(&self, x: i64, y: i64, wrap: WrapMode) -> Vec3 {
+        let x = wrap.wrap(x, self.width as i64);
+        let y = wrap.wrap(y, self.height as i64);
+        self.data[y * self.width + x]
+    }
+
+    // 纹素中心位于半整数坐标，图片第一行对应 v = 1
+    fn bilinear(&self, u: f64, v: f64, wrap: WrapMode) -> Vec3 {
+        let x = u * self.width as f64 - 0.5;
+        let y = (1. - v) * self.height as f64 - 0.5;
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let tx = x - x0;
+        let ty = y - y0;
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        let top = self.texel(x0, y0, wrap) * (1. - tx) + self.texel(x0 + 1, y0, wrap) * tx;
+        let bottom =
+            self.texel(x0, y0 + 1, wrap) * (1. - tx) + self.texel(x0 + 1, y0 + 1, wrap) * tx;
+        top * (1. - ty) + bottom * ty
+    }
+
+    // 2x2 平均得到下一层，奇数尺寸时边缘按 wrap 取值
+    fn downsample(&self, wrap: WrapMode) -> MipLevel {
+        let width = (self.width - self.width / 2).max(1);
+        let height = (self.height - self.height / 2).max(1);
+        let mut data = Vec::with_capacity(width * height);
+        for y in 0..height as i64 {
+            for x in 0..width as i64 {
+                data.push(
+                    (self.texel(2 * x, 2 * y, wrap)
+                        + self.texel(2 * x + 1, 2 * y, wrap)
+                        + self.texel(2 * x, 2 * y + 1, wrap)
+                        + self.texel(2 * x + 1, 2 * y + 1, wrap))
+                        * 0.25,
+                );
+            }
+        }
+        MipLevel {
+            width,
+            height,
+            data,
+        }
+    }
+}
+
+pub struct ImageTexture {
+    pub levels: Vec<MipLevel>, // 第 0 层为原图
+    pub wrap: WrapMode,
+}
+
+impl ImageTexture {
+    // srgb 为 true 时按颜色贴图处理，把 sRGB 编码转为线性；法线、粗糙度等数据贴图应传 false
+    // 内置场景的贴图都是程序生成的，暂时没有读图片文件的地方
+    #[allow(dead_code)]
+    pub fn new(path: &str, wrap: WrapMode, srgb: bool) -> image::ImageResult<Self> {
+        let img = image::open(path)?.to_rgb8();
+        let (width, height) = img.dimensions();
+        let data = img
+            .pixels()
+            .map(|pixel| {
+                let mut color = Vec3::zero();
+                for c in 0..3 {
+                    let value = pixel[c] as f64 / 255.;
+                    color[c] = if srgb {
+                        utility::srgb_to_linear(value)
+                    } else {
+                        value
+                    };
+                }
+                color
+            })
+            .collect();
+        Ok(ImageTexture::from_data(
+            width as usize,
+            height as usize,
+            data,
+            wrap,
+        ))
+    }
+
+    pub fn from_data(width: usize, height: usize, data: Vec<Vec3>, wrap: WrapMode) -> Self {
+        let mut levels = vec![MipLevel {
+            width,
+            height,
+            data,
+        }];
+        loop {
+            let last = levels.last().unwrap();
+            if last.width <= 1 && last.height <= 1 {
+                break;
+            }
+            let next = last.downsample(wrap);
+            levels.push(next);
+        }
+        Self { levels, wrap }
+    }
+
+    pub fn width(&self) -> usize {
+        self.levels[0].width
+    }
+
+    pub fn height(&self) -> usize {
+        self.levels[0].height
+    }
+
+    // 在连续的层级 level 上做三线性插值
+    pub fn trilinear(&self, u: f64, v: f64, level: f64) -> Vec3 {
+        let max_level = (self.levels.len() - 1) as f64;
+        let level = utility::clamp(level, 0., max_level);
+        let l0 = level.floor();
+        let t = level - l0;
+        let l0 = l0 as usize;
+        let c0 = self.levels[l0].bilinear(u, v, self.wrap);
+        if t == 0. {
+            return c0;
+        }
+        c0 * (1. - t) + self.levels[l0 + 1].bilinear(u, v, self.wrap) * t
+    }
+}
+
+impl Texture for ImageTexture {
+    fn get_color_value(&self, u: f64, v: f64, _p: Vec3) -> Vec3 {
+        if self.levels[0].data.is_empty() {
+            return Vec3::new(0., 1., 1.); // 没有数据时显示青色便于调试
+        }
+        self.levels[0].bilinear(u, v, self.wrap)
+    }
+
+    // 足迹在纹素空间中是一个平行四边形，沿长轴取若干个三线性采样求平均
+    fn get_filtered_value(&self, u: f64, v: f64, p: Vec3, footprint: &Footprint) -> Vec3 {
+        if self.levels[0].data.is_empty() {
+            return self.get_color_value(u, v, p);
+        }
+        let (width, height) = (self.width() as f64, self.height() as f64);
+        let ax = (footprint.dudx * width, footprint.dvdx * height);
+        let ay = (footprint.dudy * width, footprint.dvdy * height);
+        let len_x = (ax.0 * ax.0 + ax.1 * ax.1).sqrt();
+        let len_y = (ay.0 * ay.0 + ay.1 * ay.1).sqrt();
+        let (major, major_len, minor_len) = if len_x > len_y {
+            (ax, len_x, len_y)
+        } else {
+            (ay, len_y, len_x)
+        };
+        if major_len <= 1e-8 {
+            return self.get_color_value(u, v, p);
+        }
+
+        let probes = ((major_len / minor_len.max(1e-8)).ceil() as usize).max(1);
+        let probes = probes.min(MAX_ANISOTROPY);
+        let filter_width = (major_len / probes as f64).max(minor_len);
+        let level = filter_width.max(1e-8).log2();
+
+        let mut color = Vec3::zero();
+        for i in 0..probes {
+            let offset = (i as f64 + 0.5) / probes as f64 - 0.5;
+            color += self.trilinear(
+                u + offset * major.0 / width,
+                v + offset * major.1 / height,
+                level,
+            );
+        }
+        color / probes as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 纹素中心的值等于它的 u，双线性插值和每一层 MIP 都精确地还原出 u
+    fn gradient(width: usize, height: usize) -> ImageTexture {
+        let mut data = Vec::with_capacity(width * height);
+        for _ in 0..height {
+            for x in 0..width {
+                let u = (x as f64 + 0.5) / width as f64;
+                data.push(Vec3::new(u, u, u));
+            }
+        }
+        ImageTexture::from_data(width, height, data, WrapMode::Clamp)
+    }
+
+    // 超出范围的纹素下标按三种方式折回
+    #[test]
+    fn wrap_modes_give_the_expected_texels() {
+        let cases = [
+            (-5, [3, 0, 3]),
+            (-1, [3, 0, 0]),
+            (0, [0, 0, 0]),
+            (3, [3, 3, 3]),
+            (4, [0, 3, 3]),
+            (6, [2, 3, 1]),
+            (9, [1, 3, 1]),
+        ];
+        for &(i, expected) in &cases {
+            assert_eq!(WrapMode::Repeat.wrap(i, 4), expected[0], "repeat {}", i);
+            assert_eq!(WrapMode::Clamp.wrap(i, 4), expected[1], "clamp {}", i);
+            assert_eq!(WrapMode::Mirror.wrap(i, 4), expected[2], "mirror {}", i);
+        }
+    }
+
+    // MIP 金字塔每层减半直到 1x1，最后一层是整张图的平均
+    #[test]
+    fn mip_levels_average_down_to_one_texel() {
+        let data = (0..12).map(|i| Vec3::new(i as f64, 0., 1.)).collect();
+        let texture = ImageTexture::from_data(4, 3, data, WrapMode::Repeat);
+        let sizes: Vec<(usize, usize)> = texture
+            .levels
+            .iter()
+            .map(|level| (level.width, level.height))
+            .collect();
+        assert_eq!(sizes, vec![(4, 3), (2, 2), (1, 1)]);
+        // 高度为奇数，第 1 层第二行用到的原图第 3 行按 Repeat 折回第 0 行
+        assert!(texture.levels[1].data[0] == Vec3::new(2.5, 0., 1.));
+        assert!(texture.levels[1].data[2] == Vec3::new(4.5, 0., 1.));
+    }
+
+    // 沿 u 拉长的足迹在线性渐变上取平均，结果仍是足迹中心的值
+    #[test]
+    fn anisotropic_footprint_averages_around_its_centre() {
+        let texture = gradient(64, 64);
+        let footprint = Footprint {
+            dudx: 0.2,
+            dvdy: 0.02,
+            ..Default::default()
+        };
+        for &u in &[0.3, 0.5, 0.7] {
+            let color = texture.get_filtered_value(u, 0.5, Vec3::zero(), &footprint);
+            assert!((color.x - u).abs() < 1e-6, "{} at u = {}", color.x, u);
+        }
+    }
+}
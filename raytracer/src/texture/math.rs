@@ -0,0 +1,138 @@
+use std::rc::Rc;
+
+use super::{Footprint, Texture};
+use crate::{basic::vec::Vec3, utility};
+
+// 按 factor 的第一个通道在两个纹理间线性插值
+pub struct MixTexture {
+    pub first: Rc<dyn Texture>,
+    pub second: Rc<dyn Texture>,
+    pub factor: Rc<dyn Texture>,
+}
+
+impl Texture for MixTexture {
+    fn get_color_value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        let t = self.factor.get_color_value(u, v, p).x;
+        self.first.get_color_value(u, v, p) * (1. - t) + self.second.get_color_value(u, v, p) * t
+    }
+
+    fn get_filtered_value(&self, u: f64, v: f64, p: Vec3, footprint: &Footprint) -> Vec3 {
+        let t = self.factor.get_filtered_value(u, v, p, footprint).x;
+        self.first.get_filtered_value(u, v, p, footprint) * (1. - t)
+            + self.second.get_filtered_value(u, v, p, footprint) * t
+    }
+}
+
+// 两个纹理逐通道相乘
+pub struct Multiply {
+    pub first: Rc<dyn Texture>,
+    pub second: Rc<dyn Texture>,
+}
+
+impl Texture for Multiply {
+    fn get_color_value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        Vec3::elemul(
+            self.first.get_color_value(u, v, p),
+            self.second.get_color_value(u, v, p),
+        )
+    }
+
+    fn get_filtered_value(&self, u: f64, v: f64, p: Vec3, footprint: &Footprint) -> Vec3 {
+        Vec3::elemul(
+            self.first.get_filtered_value(u, v, p, footprint),
+            self.second.get_filtered_value(u, v, p, footprint),
+        )
+    }
+}
+
+// 把输入从 [from.0, from.1] 线性映射到 [to.0, to.1]，逐通道计算
+pub struct Remap {
+    pub input: Rc<dyn Texture>,
+    pub from: (f64, f64),
+    pub to: (f64, f64),
+    pub clamp: bool,
+}
+
+impl Remap {
+    fn remap(&self, x: f64) -> f64 {
+        // 输入范围退化为一个点时无法映射，输出 to.0
+        let range = self.from.1 - self.from.0;
+        if range == 0. || range.is_nan() {
+            return self.to.0;
+        }
+        let mut t = (x - self.from.0) / range;
+        if self.clamp {
+            t = utility::clamp(t, 0., 1.);
+        }
+        self.to.0 + (self.to.1 - self.to.0) * t
+    }
+
+    fn remap_color(&self, c: Vec3) -> Vec3 {
+        Vec3::new(self.remap(c.x), self.remap(c.y), self.remap(c.z))
+    }
+}
+
+impl Texture for Remap {
+    fn get_color_value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        self.remap_color(self.input.get_color_value(u, v, p))
+    }
+
+    fn get_filtered_value(&self, u: f64, v: f64, p: Vec3, footprint: &Footprint) -> Vec3 {
+        self.remap_color(self.input.get_filtered_value(u, v, p, footprint))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::solid_color::SolidColor;
+
+    fn solid(value: f64) -> Rc<dyn Texture> {
+        Rc::new(SolidColor::new(value, value, value))
+    }
+
+    fn remap(input: f64, from: (f64, f64), clamp: bool) -> f64 {
+        Remap {
+            input: solid(input),
+            from,
+            to: (2., 4.),
+            clamp,
+        }
+        .get_color_value(0., 0., Vec3::zero())
+        .x
+    }
+
+    #[test]
+    fn remap_maps_the_range() {
+        assert!(remap(0.25, (0., 1.), false) == 2.5);
+        assert!(remap(1.5, (0., 1.), false) == 5.);
+        assert!(remap(1.5, (0., 1.), true) == 4.);
+        // 反向的范围同样可以映射
+        assert!(remap(0.25, (1., 0.), false) == 3.5);
+    }
+
+    // 输入范围退化时不再除以零
+    #[test]
+    fn degenerate_range_gives_the_lower_output() {
+        for &clamp in &[false, true] {
+            assert!(remap(0.5, (0.5, 0.5), clamp) == 2.);
+            assert!(remap(0.7, (0.5, 0.5), clamp) == 2.);
+            assert!(remap(0.7, (f64::NAN, 0.5), clamp) == 2.);
+        }
+    }
+
+    #[test]
+    fn mix_and_multiply() {
+        let mix = MixTexture {
+            first: solid(0.2),
+            second: solid(1.),
+            factor: solid(0.25),
+        };
+        assert!((mix.get_color_value(0., 0., Vec3::zero()).x - 0.4).abs() < 1e-12);
+        let product = Multiply {
+            first: Rc::new(SolidColor::new(0.5, 1., 2.)),
+            second: Rc::new(SolidColor::new(0.5, 0.25, 2.)),
+        };
+        assert!(product.get_color_value(0., 0., Vec3::zero()) == Vec3::new(0.25, 0.25, 4.));
+    }
+}
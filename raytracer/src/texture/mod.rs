@@ -1,8 +1,35 @@
 use crate::basic::vec::Vec3;
 pub mod checker;
+pub mod image_texture;
+pub mod math;
+pub mod noise;
+pub mod pattern;
 pub mod perlin;
 pub mod solid_color;
+pub mod transform;
+
+// 一个像素在表面上覆盖的范围：纹理坐标和世界坐标对屏幕 x、y 的偏导
+#[derive(Copy, Clone, Default)]
+pub struct Footprint {
+    pub dudx: f64,
+    pub dudy: f64,
+    pub dvdx: f64,
+    pub dvdy: f64,
+    pub dpdx: Vec3,
+    pub dpdy: Vec3,
+}
+
+impl Footprint {
+    // 世界空间中的滤波宽度
+    pub fn width(&self) -> f64 {
+        self.dpdx.length().max(self.dpdy.length())
+    }
+}
 
 pub trait Texture {
     fn get_color_value(&self, u: f64, v: f64, p: Vec3) -> Vec3;
+    // 在足迹范围内取平均，默认不做滤波
+    fn get_filtered_value(&self, u: f64, v: f64, p: Vec3, _footprint: &Footprint) -> Vec3 {
+        self.get_color_value(u, v, p)
+    }
 }
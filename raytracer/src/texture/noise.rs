@@ -0,0 +1,240 @@
+use super::{perlin::Perlin, Footprint, Texture};
+use crate::{basic::vec::Vec3, utility};
+
+// 足迹宽度为 width 时频率为 frequency 的一层噪声的权重，超过 Nyquist 频率的层淡出
+fn octave_fade(frequency: f64, width: f64) -> f64 {
+    utility::clamp(2. - 4. * frequency * width, 0., 1.)
+}
+
+// 分形布朗运动：逐层提高频率、降低振幅叠加 Perlin 噪声，输出映射到 [0, 1]
+pub struct Fbm {
+    pub noise: Perlin,
+    pub scale: f64,
+    pub octaves: i32,
+    pub lacunarity: f64, // 相邻两层的频率比
+    pub gain: f64,       // 相邻两层的振幅比
+}
+
+impl Fbm {
+    pub fn new(scale: f64, seed: u64) -> Self {
+        Self {
+            noise: Perlin::new(seed),
+            scale,
+            octaves: 6,
+            lacunarity: 2.,
+            gain: 0.5,
+        }
+    }
+
+    pub fn value(&self, p: Vec3, width: f64) -> f64 {
+        let mut sum = 0.;
+        let mut total = 0.;
+        let mut amplitude = 1.;
+        let mut frequency = self.scale;
+        for _i in 0..self.octaves {
+            let fade = octave_fade(frequency, width);
+            if fade <= 0. {
+                break;
+            }
+            sum += fade * amplitude * self.noise.noise(p * frequency);
+            total += amplitude;
+            amplitude *= self.gain;
+            frequency *= self.lacunarity;
+        }
+        if total == 0. {
+            return 0.5;
+        }
+        utility::clamp(0.5 * (1. + sum / total), 0., 1.)
+    }
+}
+
+impl Texture for Fbm {
+    fn get_color_value(&self, _u: f64, _v: f64, p: Vec3) -> Vec3 {
+        Vec3::ones() * self.value(p, 0.)
+    }
+
+    fn get_filtered_value(&self, _u: f64, _v: f64, p: Vec3, footprint: &Footprint) -> Vec3 {
+        Vec3::ones() * self.value(p, footprint.width())
+    }
+}
+
+// 脊状多重分形 (Musgrave)，用 1 - |noise| 得到山脊，每层的权重由上一层的信号决定
+pub struct RidgedMultifractal {
+    pub noise: Perlin,
+    pub scale: f64,
+    pub octaves: i32,
+    pub lacunarity: f64,
+    pub gain: f64,
+}
+
+impl RidgedMultifractal {
+    pub fn new(scale: f64, seed: u64) -> Self {
+        Self {
+            noise: Perlin::new(seed),
+            scale,
+            octaves: 6,
+            lacunarity: 2.,
+            gain: 0.5,
+        }
+    }
+
+    pub fn value(&self, p: Vec3, width: f64) -> f64 {
+        let mut sum = 0.;
+        let mut total = 0.;
+        let mut amplitude = 1.;
+        let mut frequency = self.scale;
+        let mut weight = 1.;
+        for _i in 0..self.octaves {
+            let fade = octave_fade(frequency, width);
+            if fade <= 0. {
+                break;
+            }
+            let mut signal = 1. - self.noise.noise(p * frequency).abs();
+            signal = signal * signal * weight;
+            weight = utility::clamp(signal * 2., 0., 1.);
+            sum += fade * amplitude * signal;
+            total += amplitude;
+            amplitude *= self.gain;
+            frequency *= self.lacunarity;
+        }
+        if total == 0. {
+            return 0.;
+        }
+        utility::clamp(sum / total, 0., 1.)
+    }
+}
+
+impl Texture for RidgedMultifractal {
+    fn get_color_value(&self, _u: f64, _v: f64, p: Vec3) -> Vec3 {
+        Vec3::ones() * self.value(p, 0.)
+    }
+
+    fn get_filtered_value(&self, _u: f64, _v: f64, p: Vec3, footprint: &Footprint) -> Vec3 {
+        Vec3::ones() * self.value(p, footprint.width())
+    }
+}
+
+// 内置场景只用到 F2MinusF1
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq)]
+pub enum WorleyMode {
+    F1,        // 到最近特征点的距离，形成细胞
+    F2,        // 到第二近特征点的距离
+    F2MinusF1, // 两者之差，形成细胞边界的网格
+}
+
+// Worley（细胞 / Voronoi）噪声，每个整数格子里有一个由哈希决定的特征点
+pub struct Worley {
+    pub scale: f64,
+    pub seed: u64,
+    pub mode: WorleyMode,
+}
+
+impl Worley {
+    fn hash(&self, x: i64, y: i64, z: i64, axis: u64) -> f64 {
+        let mut hash = self.seed ^ axis.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        for &c in &[x, y, z] {
+            hash ^= (c as u64).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            hash = (hash ^ (hash >> 31)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            hash ^= hash >> 29;
+        }
+        // 取高 53 位得到 [0, 1) 内的数
+        (hash >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn value(&self, p: Vec3) -> f64 {
+        let p = p * self.scale;
+        let cell = (p.x.floor() as i64, p.y.floor() as i64, p.z.floor() as i64);
+        let mut f1 = f64::INFINITY;
+        let mut f2 = f64::INFINITY;
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let (x, y, z) = (cell.0 + dx, cell.1 + dy, cell.2 + dz);
+                    let feature = Vec3::new(
+                        x as f64 + self.hash(x, y, z, 0),
+                        y as f64 + self.hash(x, y, z, 1),
+                        z as f64 + self.hash(x, y, z, 2),
+                    );
+                    let distance = (feature - p).length();
+                    if distance < f1 {
+                        f2 = f1;
+                        f1 = distance;
+                    } else if distance < f2 {
+                        f2 = distance;
+                    }
+                }
+            }
+        }
+        let value = match self.mode {
+            WorleyMode::F1 => f1,
+            WorleyMode::F2 => f2,
+            WorleyMode::F2MinusF1 => f2 - f1,
+        };
+        utility::clamp(value, 0., 1.)
+    }
+}
+
+impl Texture for Worley {
+    fn get_color_value(&self, _u: f64, _v: f64, p: Vec3) -> Vec3 {
+        Vec3::ones() * self.value(p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn worley(seed: u64, mode: WorleyMode) -> Worley {
+        Worley {
+            scale: 2.,
+            seed,
+            mode,
+        }
+    }
+
+    // 固定种子下的噪声值，生成方式变化时这里会失败
+    #[test]
+    fn same_seed_gives_golden_values() {
+        let p = Vec3::new(0.3, 1.7, -2.2);
+        let q = Vec3::new(4.1, -0.6, 0.9);
+        assert_eq!(Fbm::new(2., 7).value(p, 0.), 0.5036762054927092);
+        assert_eq!(Fbm::new(2., 7).value(q, 0.), 0.4728835640500843);
+        assert_eq!(
+            RidgedMultifractal::new(2., 7).value(p, 0.),
+            0.824513462718284
+        );
+        assert_eq!(
+            RidgedMultifractal::new(2., 7).value(q, 0.),
+            0.7611082707859808
+        );
+        assert_eq!(worley(7, WorleyMode::F1).value(p), 0.34740905886126905);
+        assert_eq!(worley(7, WorleyMode::F2).value(p), 0.7790749522816842);
+        assert_eq!(
+            worley(7, WorleyMode::F2MinusF1).value(q),
+            0.14862775748820256
+        );
+    }
+
+    // 种子的高 32 位也参与哈希
+    #[test]
+    fn worley_uses_the_whole_seed() {
+        let p = Vec3::new(-3.3, 2.5, 5.8);
+        let low = worley(7, WorleyMode::F1).value(p);
+        assert_ne!(low, worley(7 | 1 << 40, WorleyMode::F1).value(p));
+        assert_eq!(low, worley(7, WorleyMode::F1).value(p));
+        for &mode in &[WorleyMode::F2, WorleyMode::F2MinusF1] {
+            let value = worley(7, mode).value(p);
+            assert!((0. ..=1.).contains(&value));
+        }
+        assert!(low <= worley(7, WorleyMode::F2).value(p));
+    }
+
+    // 足迹比最低一层还宽时所有层都淡出，得到平均值
+    #[test]
+    fn wide_footprints_fade_to_the_mean() {
+        let p = Vec3::new(0.3, 1.7, -2.2);
+        assert_eq!(Fbm::new(2., 7).value(p, 1.), 0.5);
+        assert_eq!(RidgedMultifractal::new(2., 7).value(p, 1.), 0.);
+    }
+}
@@ -0,0 +1,235 @@
+use std::{f64::consts::PI, rc::Rc};
+
+use super::{perlin::Perlin, Footprint, Texture};
+use crate::{basic::vec::Vec3, utility};
+
+// 可调参数的大理石纹理，输出 [0, 1] 的灰度，可接 ColorRamp 上色
+// 与 NoiseTexture 不同，湍流也按 noise_scale 缩放
+pub struct Marble {
+    pub noise: Perlin,
+    pub frequency: f64,   // 条纹沿 axis 方向的角频率
+    pub noise_scale: f64, // 湍流的空间缩放
+    pub turbulence: f64,  // 湍流对条纹相位的扰动幅度
+    pub octaves: i32,
+    pub axis: Vec3,
+}
+
+impl Marble {
+    pub fn new(frequency: f64, seed: u64) -> Self {
+        Self {
+            noise: Perlin::new(seed),
+            frequency,
+            noise_scale: 1.,
+            turbulence: 10.,
+            octaves: 7,
+            axis: Vec3::new(0., 0., 1.),
+        }
+    }
+
+    pub fn value(&self, p: Vec3, width: f64) -> f64 {
+        let turb =
+            self.noise
+                .turb_filtered(p * self.noise_scale, self.octaves, width * self.noise_scale);
+        let phase = self.frequency * (p * self.axis) + self.turbulence * turb;
+        let fade = utility::clamp(self.frequency * width / PI - 0.5, 0., 1.);
+        0.5 * (1. + phase.sin() * (1. - fade))
+    }
+}
+
+impl Texture for Marble {
+    fn get_color_value(&self, _u: f64, _v: f64, p: Vec3) -> Vec3 {
+        Vec3::ones() * self.value(p, 0.)
+    }
+
+    fn get_filtered_value(&self, _u: f64, _v: f64, p: Vec3, footprint: &Footprint) -> Vec3 {
+        Vec3::ones() * self.value(p, footprint.width())
+    }
+}
+
+// 木纹：绕 y 轴的同心年轮，用噪声扰动半径
+pub struct Wood {
+    pub light: Rc<dyn Texture>,
+    pub dark: Rc<dyn Texture>,
+    pub noise: Perlin,
+    pub rings: f64, // 单位长度内的年轮数
+    pub noise_scale: f64,
+    pub turbulence: f64, // 噪声对年轮位置的扰动幅度（以年轮为单位）
+}
+
+impl Wood {
+    fn ring(&self, p: Vec3, width: f64) -> f64 {
+        let r = (p.x * p.x + p.z * p.z).sqrt() * self.rings
+            + self.turbulence * self.noise.noise(p * self.noise_scale);
+        let t = r - r.floor();
+        // 早材到晚材逐渐变深，再突变回早材
+        let sharp = t * t * (3. - 2. * t);
+        // 年轮比足迹还细时取平均
+        let fade = utility::clamp(2. * width * self.rings - 1., 0., 1.);
+        sharp * (1. - fade) + 0.5 * fade
+    }
+}
+
+impl Texture for Wood {
+    fn get_color_value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        let t = self.ring(p, 0.);
+        self.light.get_color_value(u, v, p) * (1. - t) + self.dark.get_color_value(u, v, p) * t
+    }
+
+    fn get_filtered_value(&self, u: f64, v: f64, p: Vec3, footprint: &Footprint) -> Vec3 {
+        let t = self.ring(p, footprint.width());
+        self.light.get_filtered_value(u, v, p, footprint) * (1. - t)
+            + self.dark.get_filtered_value(u, v, p, footprint) * t
+    }
+}
+
+// 内置场景只用到 V
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq)]
+pub enum GradientKind {
+    U,
+    V,
+    Linear { origin: Vec3, direction: Vec3 }, // direction 的长度为渐变从 0 到 1 的距离的倒数
+    Radial { center: Vec3, radius: f64 },
+}
+
+// 输出 [0, 1] 的灰度渐变，通常接 ColorRamp
+pub struct Gradient {
+    pub kind: GradientKind,
+}
+
+impl Texture for Gradient {
+    fn get_color_value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        let t = match self.kind {
+            GradientKind::U => u,
+            GradientKind::V => v,
+            GradientKind::Linear { origin, direction } => (p - origin) * direction,
+            GradientKind::Radial { center, radius } => (p - center).length() / radius,
+        };
+        Vec3::ones() * utility::clamp(t, 0., 1.)
+    }
+}
+
+// 颜色渐变表：取输入纹理的第一个通道，在按位置排序的色标之间线性插值
+pub struct ColorRamp {
+    pub input: Rc<dyn Texture>,
+    pub stops: Vec<(f64, Vec3)>,
+}
+
+impl ColorRamp {
+    pub fn new(input: Rc<dyn Texture>, mut stops: Vec<(f64, Vec3)>) -> Self {
+        // 位置不是有限数的色标无法排序，直接丢掉
+        stops.retain(|stop| stop.0.is_finite());
+        stops.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        Self { input, stops }
+    }
+
+    pub fn lookup(&self, t: f64) -> Vec3 {
+        if self.stops.is_empty() {
+            return Vec3::ones() * t;
+        }
+        let first = self.stops[0];
+        if t <= first.0 {
+            return first.1;
+        }
+        for pair in self.stops.windows(2) {
+            let (t0, c0) = pair[0];
+            let (t1, c1) = pair[1];
+            if t <= t1 {
+                let s = if t1 > t0 { (t - t0) / (t1 - t0) } else { 1. };
+                return c0 * (1. - s) + c1 * s;
+            }
+        }
+        self.stops[self.stops.len() - 1].1
+    }
+}
+
+impl Texture for ColorRamp {
+    fn get_color_value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        self.lookup(self.input.get_color_value(u, v, p).x)
+    }
+
+    fn get_filtered_value(&self, u: f64, v: f64, p: Vec3, footprint: &Footprint) -> Vec3 {
+        self.lookup(self.input.get_filtered_value(u, v, p, footprint).x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::solid_color::SolidColor;
+
+    // 固定种子下的条纹值；足迹比条纹还宽时条纹淡出为 0.5
+    #[test]
+    fn marble_golden_values() {
+        let marble = Marble::new(4., 7);
+        assert_eq!(
+            marble.value(Vec3::new(0.3, 1.7, -2.2), 0.),
+            0.07683964977064234
+        );
+        assert_eq!(
+            marble.value(Vec3::new(4.1, -0.6, 0.9), 0.),
+            0.9986760973484012
+        );
+        assert_eq!(marble.value(Vec3::new(0.3, 1.7, -2.2), 2.), 0.5);
+    }
+
+    // 色标按位置排序后插值，范围外取两端的颜色，位置无效的色标被丢掉
+    #[test]
+    fn color_ramp_interpolates_sorted_stops() {
+        let ramp = ColorRamp::new(
+            Rc::new(SolidColor::new(0., 0., 0.)),
+            vec![
+                (1., Vec3::new(0., 0., 1.)),
+                (f64::NAN, Vec3::ones()),
+                (0.5, Vec3::new(0., 1., 0.)),
+                (0., Vec3::new(1., 0., 0.)),
+            ],
+        );
+        assert_eq!(ramp.stops.len(), 3);
+        assert!(ramp.lookup(-1.) == Vec3::new(1., 0., 0.));
+        assert!(ramp.lookup(0.25) == Vec3::new(0.5, 0.5, 0.));
+        assert!(ramp.lookup(0.75) == Vec3::new(0., 0.5, 0.5));
+        assert!(ramp.lookup(2.) == Vec3::new(0., 0., 1.));
+        assert!(ramp.get_color_value(0., 0., Vec3::zero()) == Vec3::new(1., 0., 0.));
+    }
+
+    #[test]
+    fn gradients() {
+        let value = |kind, u, v, p| Gradient { kind }.get_color_value(u, v, p).x;
+        assert_eq!(value(GradientKind::U, 0.3, 0.6, Vec3::zero()), 0.3);
+        assert_eq!(value(GradientKind::V, 0.3, 0.6, Vec3::zero()), 0.6);
+        let linear = GradientKind::Linear {
+            origin: Vec3::new(1., 0., 0.),
+            direction: Vec3::new(0.5, 0., 0.),
+        };
+        assert_eq!(value(linear, 0., 0., Vec3::new(2., 5., 5.)), 0.5);
+        assert_eq!(value(linear, 0., 0., Vec3::new(0., 5., 5.)), 0.);
+        let radial = GradientKind::Radial {
+            center: Vec3::zero(),
+            radius: 2.,
+        };
+        assert_eq!(value(radial, 0., 0., Vec3::new(0., 1., 0.)), 0.5);
+        assert_eq!(value(radial, 0., 0., Vec3::new(0., 3., 0.)), 1.);
+    }
+
+    // 年轮比足迹还细时木纹退化为两种颜色的平均
+    #[test]
+    fn fine_rings_average_out() {
+        let wood = Wood {
+            light: Rc::new(SolidColor::new(1., 1., 1.)),
+            dark: Rc::new(SolidColor::new(0., 0., 0.)),
+            noise: Perlin::new(3),
+            rings: 10.,
+            noise_scale: 2.,
+            turbulence: 0.3,
+        };
+        let footprint = Footprint {
+            dpdx: Vec3::new(0.2, 0., 0.),
+            ..Default::default()
+        };
+        let p = Vec3::new(0.4, 0.1, 0.3);
+        assert_eq!(wood.get_filtered_value(0., 0., p, &footprint).x, 0.5);
+        let sharp = wood.get_color_value(0., 0., p).x;
+        assert!((0. ..=1.).contains(&sharp));
+    }
+}
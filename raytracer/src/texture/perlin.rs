@@ -1,14 +1,21 @@
 #![allow(dead_code)]
-use crate::{basic::vec::Vec3, utility};
+use crate::{
+    basic::vec::Vec3,
+    utility::{self, RandGen},
+};
+use rand::{Rng, SeedableRng};
+use std::f64::consts::PI;
 
-use super::Texture;
+use super::{Footprint, Texture};
 
 pub struct Perlin {
     pub perm_x: [i32; 256],
     pub perm_y: [i32; 256],
     pub perm_z: [i32; 256],
+    pub perm_w: [i32; 256], // 第四维（时间）的置换表
     // pub ranfloat: [f64; 256],
     pub ranvec: [Vec3; 256],
+    pub ranvec4: [[f64; 4]; 256], // 四维噪声的随机梯度
 }
 
 impl Perlin {
@@ -34,34 +41,106 @@ impl Perlin {
         Perlin::trilinear_interp(c, u, v, w)
     }
 
-    pub fn perlin_generate_perm() -> [i32; 256] {
+    // 二维噪声，取三维噪声 z = 0 的截面
+    pub fn noise_2d(&self, x: f64, y: f64) -> f64 {
+        self.noise(Vec3::new(x, y, 0.))
+    }
+
+    // 四维噪声，第四维通常用作时间，使纹理随时间连续变化
+    pub fn noise_4d(&self, p: Vec3, time: f64) -> f64 {
+        let coords = [p.x, p.y, p.z, time];
+        let mut frac = [0.; 4];
+        let mut cell = [0; 4];
+        let mut smooth = [0.; 4];
+        for axis in 0..4 {
+            let floor = coords[axis].floor();
+            frac[axis] = coords[axis] - floor;
+            cell[axis] = floor as i32;
+            smooth[axis] = frac[axis] * frac[axis] * (3. - 2. * frac[axis]);
+        }
+
+        let mut acc = 0.;
+        for corner in 0..16 {
+            let offset = [
+                corner & 1,
+                (corner >> 1) & 1,
+                (corner >> 2) & 1,
+                (corner >> 3) & 1,
+            ];
+            let index = self.perm_x[(255 & (cell[0] + offset[0])) as usize]
+                ^ self.perm_y[(255 & (cell[1] + offset[1])) as usize]
+                ^ self.perm_z[(255 & (cell[2] + offset[2])) as usize]
+                ^ self.perm_w[(255 & (cell[3] + offset[3])) as usize];
+            let gradient = self.ranvec4[index as usize];
+            let mut weight = 1.;
+            let mut dot = 0.;
+            for axis in 0..4 {
+                let o = offset[axis] as f64;
+                weight *= o * smooth[axis] + (1. - o) * (1. - smooth[axis]);
+                dot += gradient[axis] * (frac[axis] - o);
+            }
+            acc += weight * dot;
+        }
+        acc
+    }
+
+    pub fn perlin_generate_perm(rng: &mut RandGen) -> [i32; 256] {
         let mut p: [i32; 256] = [0; 256];
-        for i in 0..256 {
-            p[i] = i as i32;
+        for (i, x) in p.iter_mut().enumerate() {
+            *x = i as i32;
         }
-        Perlin::permute(p, 256);
+        Perlin::permute(&mut p, 256, rng);
         p
     }
 
-    pub fn permute(mut p: [i32; 256], n: usize) {
-        for i in (0..n).rev() {
-            let target = utility::random_int(0, i as i32) as usize;
-            let tmp = p[i];
-            p[i] = p[target];
-            p[target] = tmp;
+    // Fisher-Yates 洗牌
+    pub fn permute(p: &mut [i32; 256], n: usize, rng: &mut RandGen) {
+        for i in (1..n).rev() {
+            let target = rng.gen_range(0..=i);
+            p.swap(i, target);
         }
     }
 
-    pub fn new() -> Self {
+    // 相同的 seed 总是生成相同的噪声
+    pub fn new(seed: u64) -> Self {
+        let mut rng = RandGen::seed_from_u64(seed);
         let mut ranvec: [Vec3; 256] = [Vec3::new(0., 0., 0.); 256];
-        for i in 0..256 {
-            ranvec[i] = Vec3::unit(Vec3::random(-1., 1.));
+        for v in ranvec.iter_mut() {
+            *v = loop {
+                let p = Vec3::new(
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                );
+                let length = p.length();
+                if length > 1e-3 && length <= 1. {
+                    break p / length;
+                }
+            };
+        }
+        let mut ranvec4 = [[0.; 4]; 256];
+        for g in ranvec4.iter_mut() {
+            *g = loop {
+                let mut p = [0.; 4];
+                for x in p.iter_mut() {
+                    *x = rng.gen_range(-1.0..1.0);
+                }
+                let length = p.iter().map(|x| x * x).sum::<f64>().sqrt();
+                if length > 1e-3 && length <= 1. {
+                    for x in p.iter_mut() {
+                        *x /= length;
+                    }
+                    break p;
+                }
+            };
         }
         Self {
-            perm_x: Perlin::perlin_generate_perm(),
-            perm_y: Perlin::perlin_generate_perm(),
-            perm_z: Perlin::perlin_generate_perm(),
+            perm_x: Perlin::perlin_generate_perm(&mut rng),
+            perm_y: Perlin::perlin_generate_perm(&mut rng),
+            perm_z: Perlin::perlin_generate_perm(&mut rng),
+            perm_w: Perlin::perlin_generate_perm(&mut rng),
             ranvec,
+            ranvec4,
         }
     }
 
@@ -97,6 +176,26 @@ impl Perlin {
         }
         accum.abs()
     }
+
+    // 只叠加频率低于足迹对应的 Nyquist 频率的倍频，最后一个倍频按比例淡出
+    pub fn turb_filtered(&self, p: Vec3, depth: i32, width: f64) -> f64 {
+        let mut accum = 0.;
+        let mut tmp = p;
+        let mut weight = 1.;
+        let mut frequency = 1.;
+
+        for _i in 0..depth {
+            let fade = utility::clamp(2. - 4. * frequency * width, 0., 1.);
+            if fade <= 0. {
+                break;
+            }
+            accum += fade * weight * self.noise(tmp);
+            weight *= 0.5;
+            frequency *= 2.;
+            tmp *= 2.;
+        }
+        accum.abs()
+    }
 }
 
 //------------------------------------------------------------------
@@ -112,4 +211,75 @@ impl Texture for NoiseTexture {
         Vec3::new(1., 1., 1.) * 0.5 * (1. + (self.scale * p.z + 10. * self.noise.turb(p, 7)).sin())
         //利用正弦函数模拟出大理石的纹理
     }
+
+    fn get_filtered_value(&self, _u: f64, _v: f64, p: Vec3, footprint: &Footprint) -> Vec3 {
+        let width = footprint.width();
+        let phase = self.scale * p.z + 10. * self.noise.turb_filtered(p, 7, width);
+        // 条纹比足迹还细时退化为平均值
+        let fade = utility::clamp(self.scale * width / PI - 0.5, 0., 1.);
+        Vec3::new(1., 1., 1.) * 0.5 * (1. + phase.sin() * (1. - fade))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_permutation(perm: &[i32; 256]) -> bool {
+        let mut seen = [false; 256];
+        for &x in perm.iter() {
+            if !(0..256).contains(&x) || seen[x as usize] {
+                return false;
+            }
+            seen[x as usize] = true;
+        }
+        true
+    }
+
+    // 固定种子下的噪声值，生成方式变化时这里会失败
+    #[test]
+    fn same_seed_gives_golden_values() {
+        let perlin = Perlin::new(42);
+        assert_eq!(perlin.noise(Vec3::new(0.3, 1.7, -2.2)), -0.1950718208230499);
+        assert_eq!(
+            perlin.noise(Vec3::new(5.5, -0.25, 3.125)),
+            -0.07524543834738623
+        );
+        assert_eq!(perlin.noise_2d(0.6, 2.4), 0.18382249221796793);
+        assert_eq!(
+            perlin.noise_4d(Vec3::new(0.3, 1.7, -2.2), 0.75),
+            -0.2578175822397617
+        );
+
+        let again = Perlin::new(42);
+        assert_eq!(perlin.perm_x, again.perm_x);
+        assert_eq!(perlin.perm_w, again.perm_w);
+    }
+
+    #[test]
+    fn different_seeds_give_different_tables() {
+        let a = Perlin::new(1);
+        let b = Perlin::new(2);
+        assert_ne!(a.perm_x, b.perm_x);
+        assert_ne!(a.perm_y, b.perm_y);
+        assert_ne!(a.perm_z, b.perm_z);
+        assert_ne!(a.perm_w, b.perm_w);
+        assert_ne!(
+            a.noise(Vec3::new(0.3, 1.7, -2.2)),
+            b.noise(Vec3::new(0.3, 1.7, -2.2))
+        );
+    }
+
+    #[test]
+    fn tables_are_shuffled_permutations() {
+        let perlin = Perlin::new(7);
+        let mut identity = [0; 256];
+        for (i, x) in identity.iter_mut().enumerate() {
+            *x = i as i32;
+        }
+        for perm in &[perlin.perm_x, perlin.perm_y, perlin.perm_z, perlin.perm_w] {
+            assert!(is_permutation(perm));
+            assert_ne!(*perm, identity);
+        }
+    }
 }
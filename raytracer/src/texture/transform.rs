@@ -0,0 +1,143 @@
+use std::rc::Rc;
+
+use super::{Footprint, Texture};
+use crate::{basic::vec::Vec3, utility};
+
+// 在查询纹理前先对纹理坐标和空间坐标做缩放、旋转、平移
+// 空间坐标是材质传进来的 HitRecord::local_p，即物体坐标，物体运动时纹理不会滑动
+// 先缩放，再旋转（角度制，空间坐标依次绕 x、y、z 轴），最后平移
+pub struct TextureTransform {
+    pub texture: Rc<dyn Texture>,
+    pub uv_scale: (f64, f64),
+    pub uv_rotation: f64,
+    pub uv_offset: (f64, f64),
+    pub p_scale: Vec3,
+    pub p_rotation: Vec3,
+    pub p_offset: Vec3,
+}
+
+impl TextureTransform {
+    // 恒等变换，其余字段可以用 `..TextureTransform::new(texture)` 按需覆盖
+    pub fn new(texture: Rc<dyn Texture>) -> Self {
+        Self {
+            texture,
+            uv_scale: (1., 1.),
+            uv_rotation: 0.,
+            uv_offset: (0., 0.),
+            p_scale: Vec3::ones(),
+            p_rotation: Vec3::zero(),
+            p_offset: Vec3::zero(),
+        }
+    }
+
+    // 变换中的线性部分，偏导也按它变换
+    fn uv_linear(&self, u: f64, v: f64) -> (f64, f64) {
+        let (sin, cos) = utility::degree_to_radian(self.uv_rotation).sin_cos();
+        let (u, v) = (u * self.uv_scale.0, v * self.uv_scale.1);
+        (u * cos - v * sin, u * sin + v * cos)
+    }
+
+    fn p_linear(&self, p: Vec3) -> Vec3 {
+        let mut p = Vec3::elemul(p, self.p_scale);
+        for axis in 0..3 {
+            let (sin, cos) = utility::degree_to_radian(self.p_rotation[axis]).sin_cos();
+            let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+            let (pa, pb) = (p[a], p[b]);
+            p[a] = pa * cos - pb * sin;
+            p[b] = pa * sin + pb * cos;
+        }
+        p
+    }
+
+    fn transform(&self, u: f64, v: f64, p: Vec3) -> (f64, f64, Vec3) {
+        let (u, v) = self.uv_linear(u, v);
+        (
+            u + self.uv_offset.0,
+            v + self.uv_offset.1,
+            self.p_linear(p) + self.p_offset,
+        )
+    }
+}
+
+impl Texture for TextureTransform {
+    fn get_color_value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        let (u, v, p) = self.transform(u, v, p);
+        self.texture.get_color_value(u, v, p)
+    }
+
+    fn get_filtered_value(&self, u: f64, v: f64, p: Vec3, footprint: &Footprint) -> Vec3 {
+        let (u, v, p) = self.transform(u, v, p);
+        let (dudx, dvdx) = self.uv_linear(footprint.dudx, footprint.dvdx);
+        let (dudy, dvdy) = self.uv_linear(footprint.dudy, footprint.dvdy);
+        let footprint = Footprint {
+            dudx,
+            dudy,
+            dvdx,
+            dvdy,
+            dpdx: self.p_linear(footprint.dpdx),
+            dpdy: self.p_linear(footprint.dpdy),
+        };
+        self.texture.get_filtered_value(u, v, p, &footprint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 把查询到的坐标和足迹原样作为颜色返回
+    struct Probe;
+
+    impl Texture for Probe {
+        fn get_color_value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+            Vec3::new(u, v, p.length())
+        }
+
+        fn get_filtered_value(&self, _u: f64, _v: f64, p: Vec3, footprint: &Footprint) -> Vec3 {
+            Vec3::new(footprint.dudx, footprint.dvdx, footprint.dpdx * p)
+        }
+    }
+
+    fn near(a: Vec3, b: Vec3) -> bool {
+        (a - b).length() < 1e-12
+    }
+
+    // new 是恒等变换
+    #[test]
+    fn new_is_the_identity() {
+        let transform = TextureTransform::new(Rc::new(Probe));
+        let p = Vec3::new(0.3, -0.2, 0.5);
+        assert!(near(
+            transform.get_color_value(0.3, 0.7, p),
+            Vec3::new(0.3, 0.7, p.length())
+        ));
+    }
+
+    // 先缩放，再旋转，最后平移
+    #[test]
+    fn uv_and_position_are_scaled_rotated_and_offset() {
+        let transform = TextureTransform {
+            uv_scale: (2., 3.),
+            uv_rotation: 90.,
+            uv_offset: (0.1, 0.2),
+            p_scale: Vec3::new(2., 1., 1.),
+            p_rotation: Vec3::new(0., 0., 90.),
+            p_offset: Vec3::new(0., 0., 1.),
+            ..TextureTransform::new(Rc::new(Probe))
+        };
+        // (0.5, 0.25) 缩放为 (1, 0.75)，旋转 90 度为 (-0.75, 1)
+        let (u, v, p) = transform.transform(0.5, 0.25, Vec3::new(1., 0., 0.));
+        assert!((u + 0.65).abs() < 1e-12 && (v - 1.2).abs() < 1e-12);
+        // (1, 0, 0) 缩放为 (2, 0, 0)，绕 z 轴旋转 90 度为 (0, 2, 0)
+        assert!(near(p, Vec3::new(0., 2., 1.)));
+
+        // 偏导只经过线性部分，不受平移影响
+        let footprint = Footprint {
+            dudx: 0.1,
+            dpdx: Vec3::new(1., 0., 0.),
+            ..Default::default()
+        };
+        let filtered = transform.get_filtered_value(0.5, 0.25, Vec3::new(1., 0., 0.), &footprint);
+        assert!(near(filtered, Vec3::new(0., 0.2, 4.)));
+    }
+}
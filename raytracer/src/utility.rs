@@ -1,5 +1,6 @@
 #![allow(dead_code)]
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64Mcg;
 use std::f64::consts::PI;
 
 use crate::basic::vec::Vec3;
@@ -8,17 +9,32 @@ pub fn degree_to_radian(degree: f64) -> f64 {
     degree * PI / 180.
 }
 
-pub fn random_double(min: f64, max: f64) -> f64 {
-    let mut rng = rand::thread_rng();
+// 渲染中所有随机数都来自显式传入的生成器，保证同一种子下结果逐位一致
+// PCG 的输出序列有明确的定义，不随平台和 rand 的版本变化（SmallRng、StdRng 都不保证这一点）
+pub type RandGen = Pcg64Mcg;
+
+pub fn random_double<R: Rng + ?Sized>(rng: &mut R, min: f64, max: f64) -> f64 {
     min + rng.gen::<f64>() * (max - min)
 }
 
-pub fn random_int(min: i32, max: i32) -> i32 {
-    let mut rng = rand::thread_rng();
+pub fn random_int<R: Rng + ?Sized>(rng: &mut R, min: i32, max: i32) -> i32 {
     rng.gen_range(min..max + 1)
     // 左闭右开，即 [min, max + 1)
 }
 
+// SplitMix64 的混合函数，把相近的整数打散
+pub fn hash_u64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+// 每个样本的随机数只由全局种子、像素编号和样本编号决定，与渲染顺序和线程数无关
+pub fn sample_rng(seed: u64, pixel: u64, sample: u64) -> RandGen {
+    RandGen::seed_from_u64(hash_u64(seed ^ hash_u64(pixel ^ hash_u64(sample))))
+}
+
 pub fn fmin(a: f64, b: f64) -> f64 {
     if a <= b {
         a
@@ -43,6 +59,15 @@ pub fn clamp(x: f64, min: f64, max: f64) -> f64 {
     }
     x
 }
+// sRGB 编码的颜色分量转为线性值
+pub fn srgb_to_linear(x: f64) -> f64 {
+    if x <= 0.04045 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
+
 pub fn get_pixel_color(color: Vec3, samples_per_pixel: u32) -> [u8; 3] {
     let scale = 1. / samples_per_pixel as f64;
     let r = (color.x * scale).sqrt();
@@ -54,3 +79,8 @@ pub fn get_pixel_color(color: Vec3, samples_per_pixel: u32) -> [u8; 3] {
         (clamp(b, 0., 0.999) * 256.).floor() as u8,
     ]
 }
+
+// 线性 RGB 的亮度 (Rec. 709)
+pub fn luminance(color: Vec3) -> f64 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}